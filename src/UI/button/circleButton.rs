@@ -10,6 +10,9 @@ use crate::Vec2;
 use super::{invert, display, in_bounds};
 use super::{ManipMode, Mode};
 use super::Button;
+use super::super::message::Message;
+use super::super::storage::Storage;
+use crate::World;
 
 const DEFAULT_RADIUS: f64 = 50.0;
 
@@ -31,7 +34,7 @@ impl Button for CircleButton
     fn mutTexture(&mut self) -> &mut bmp::Image {&mut self.texture}
     fn clicked(&self) -> bool {self.clicked}
     
-    fn clickDown(&mut self, state: &mut Mode, _objects: &Vec<Shape>)
+    fn clickDown(&mut self, state: &mut Mode, _world: &mut World, _storage: &mut Storage) -> Option<Message>
     {
         invert(&mut self.texture);
 
@@ -46,7 +49,8 @@ impl Button for CircleButton
                 println!("Pause first!");
                 Mode::Unpaused
             },
-        }
+        };
+        None
     }
 
 }
@@ -67,10 +71,10 @@ impl UI for CircleButton
         in_bounds(self, click)
     }
 
-    fn click_down(&mut self, state: &mut Mode, objects: &Vec<Shape>)
+    fn click_down(&mut self, state: &mut Mode, world: &mut World, storage: &mut Storage) -> Option<Message>
     {
-        self.clickDown(state, objects);
         self.clicked = true;
+        self.clickDown(state, world, storage)
     }
     fn click_up(&mut self, _objects: &Vec<Shape>)
     {
@@ -0,0 +1,75 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+
+use super::Displayable;
+use super::UI;
+use crate::Shape;
+
+use super::{invert, display, in_bounds};
+use super::Mode;
+use super::Button;
+use super::super::message::Message;
+use super::super::storage::Storage;
+use crate::World;
+
+#[derive(Debug)]
+//Undefined behaviour if bottomRight is not below and to the right of topLeft
+pub struct LoadButton
+{
+    topLeft: Point,
+    bottomRight: Point,
+    texture: bmp::Image,
+    clicked: bool,
+}
+
+impl Button for LoadButton
+{
+    fn topLeft(&self) -> Point {self.topLeft}
+    fn bottomRight(&self) -> Point {self.bottomRight}
+    fn texture(&self) -> &bmp::Image {&self.texture}
+    fn mutTexture(&mut self) -> &mut bmp::Image {&mut self.texture}
+    fn clicked(&self) -> bool {self.clicked}
+
+    fn clickDown(&mut self, state: &mut Mode, world: &mut World, storage: &mut Storage) -> Option<Message>
+    {
+        invert(&mut self.texture);
+        Some(storage.load(world, state))
+    }
+
+}
+
+impl Displayable for LoadButton
+{
+    //Scales the input image
+    fn display(&self) -> Vec<(Point, Color)>
+    {
+        display(self)
+    }
+}
+
+impl UI for LoadButton
+{
+    fn in_bounds(&self, click: Point) -> bool
+    {
+        in_bounds(self, click)
+    }
+
+    fn click_down(&mut self, state: &mut Mode, world: &mut World, storage: &mut Storage) -> Option<Message>
+    {
+        self.clicked = true;
+        self.clickDown(state, world, storage)
+    }
+    fn click_up(&mut self, _objects: &Vec<Shape>)
+    {
+        invert(&mut self.texture);
+        self.clicked = false;
+    }
+}
+
+impl LoadButton
+{
+    pub fn new(topLeft: Point, bottomRight: Point, texture: &str) -> LoadButton
+    {
+        LoadButton{topLeft, bottomRight, texture: bmp::open(texture).unwrap_or_else(|_| panic!("Error opening texture: {}", texture)), clicked: false}
+    }
+}
@@ -4,8 +4,12 @@ pub mod pauseButton;
 pub mod moveButton;
 pub mod rectButton;
 pub mod circleButton;
+pub mod saveButton;
+pub mod loadButton;
 use crate::{ManipMode, Mode};
-use crate::Shape;
+use crate::World;
+use super::message::Message;
+use super::storage::Storage;
 
 use crate::Vec2;
 use super::Displayable;
@@ -20,7 +24,7 @@ pub trait Button: UI {
     fn texture(&self) -> &bmp::Image;
     fn mutTexture(&mut self) -> &mut bmp::Image;
     fn clicked(&self) -> bool;
-    fn clickDown(&mut self, state: &mut Mode, objects: &Vec<Shape>);
+    fn clickDown(&mut self, state: &mut Mode, world: &mut World, storage: &mut Storage) -> Option<Message>;
 }
 
 fn display(button: &impl Button) -> Vec<(Point, Color)> {
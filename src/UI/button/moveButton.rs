@@ -8,6 +8,9 @@ use crate::Shape;
 use super::{invert, display, in_bounds};
 use super::{ManipMode, Mode};
 use super::Button;
+use super::super::message::Message;
+use super::super::storage::Storage;
+use crate::World;
 
 #[derive(Debug)]
 //Undefined behaviour if bottomRight is not below and to the right of topLeft
@@ -27,13 +30,14 @@ impl Button for MoveButton
     fn mutTexture(&mut self) -> &mut bmp::Image {&mut self.texture}
     fn clicked(&self) -> bool {self.clicked}
     
-    fn clickDown(&mut self, state: &mut Mode, _objects: &Vec<Shape>)
+    fn clickDown(&mut self, state: &mut Mode, _world: &mut World, _storage: &mut Storage) -> Option<Message>
     {
         invert(&mut self.texture);
         *state = match *state {
             Mode::Paused(_) => Mode::Paused(ManipMode::Move),
             Mode::Unpaused => Mode::Unpaused,
-        }
+        };
+        None
     }
 
 }
@@ -54,10 +58,10 @@ impl UI for MoveButton
         in_bounds(self, click)
     }
 
-    fn click_down(&mut self, state: &mut Mode, objects: &Vec<Shape>)
+    fn click_down(&mut self, state: &mut Mode, world: &mut World, storage: &mut Storage) -> Option<Message>
     {
-        self.clickDown(state, objects);
         self.clicked = true;
+        self.clickDown(state, world, storage)
     }
     fn click_up(&mut self, _objects: &Vec<Shape>)
     {
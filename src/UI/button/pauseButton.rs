@@ -7,8 +7,11 @@ use super::UI;
 use super::{invert, display, in_bounds};
 use super::{ManipMode, Mode};
 use super::Button;
+use super::super::message::Message;
+use super::super::storage::Storage;
+use crate::World;
 
-use super::super::super::physics::detectIntersections;
+use physics_2d::displayable::physics::detectIntersections;
 
 #[derive(Debug)]
 //Undefined behaviour if bottomRight is not below and to the right of topLeft
@@ -28,12 +31,12 @@ impl Button for PauseButton
     fn mutTexture(&mut self) -> &mut bmp::Image {&mut self.texture}
     fn clicked(&self) -> bool {self.clicked}
     
-    fn clickDown(&mut self, state: &mut Mode, objects: &Vec<Shape>)
+    fn clickDown(&mut self, state: &mut Mode, world: &mut World, _storage: &mut Storage) -> Option<Message>
     {
         invert(&mut self.texture);
         let new = match *state {
             Mode::Paused(_) => {
-                if detectIntersections(objects).len() == 0 {
+                if detectIntersections(&world.objects).len() == 0 {
                     Some(Mode::Unpaused)
                 }
                 else {
@@ -46,6 +49,7 @@ impl Button for PauseButton
         if let Some(newState) = new {
             *state = newState;
         }
+        None
     }
 
 }
@@ -66,10 +70,10 @@ impl UI for PauseButton
         in_bounds(self, click)
     }
 
-    fn click_down(&mut self, state: &mut Mode, objects: &Vec<Shape>)
+    fn click_down(&mut self, state: &mut Mode, world: &mut World, storage: &mut Storage) -> Option<Message>
     {
-        self.clickDown(state, objects);
         self.clicked = true;
+        self.clickDown(state, world, storage)
     }
     fn click_up(&mut self, _objects: &Vec<Shape>)
     {
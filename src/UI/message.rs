@@ -0,0 +1,72 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+
+use super::Displayable;
+use physics_2d::WINDOW_DIMENSIONS;
+
+use std::time::{Duration, Instant};
+
+//How long a message stays on screen, and how thick the frame it is shown with is
+const MESSAGE_TIME: Duration = Duration::from_millis(2000);
+const FRAME: i32 = 6;
+
+//Tells the user how something they did went. There is no font to draw the text with, so it goes
+//to the terminal and the window flashes a green or red frame
+#[derive(Debug)]
+pub struct Message
+{
+    text: String,
+    success: bool,
+    shown: Instant,
+}
+
+impl Message
+{
+    pub fn success(text: String) -> Message
+    {
+        println!("{}", text);
+        Message{text, success: true, shown: Instant::now()}
+    }
+
+    pub fn failure(text: String) -> Message
+    {
+        eprintln!("{}", text);
+        Message{text, success: false, shown: Instant::now()}
+    }
+
+    #[inline]
+    pub fn text(&self) -> &str
+    {
+        &self.text
+    }
+
+    pub fn expired(&self) -> bool
+    {
+        self.shown.elapsed() > MESSAGE_TIME
+    }
+}
+
+impl Displayable for Message
+{
+    fn display(&self) -> Vec<(Point, Color)>
+    {
+        let (width, height) = (WINDOW_DIMENSIONS.0 as i32, WINDOW_DIMENSIONS.1 as i32);
+        let mut points = Vec::new();
+        for x in 0..width {
+            for y in (0..FRAME).chain(height-FRAME..height) {
+                points.push((Point::new(x, y), self.color()));
+            }
+        }
+        for y in FRAME..height-FRAME {
+            for x in (0..FRAME).chain(width-FRAME..width) {
+                points.push((Point::new(x, y), self.color()));
+            }
+        }
+        points
+    }
+
+    fn color(&self) -> Color
+    {
+        if self.success { Color::RGB(60, 220, 90) } else { Color::RGB(230, 50, 50) }
+    }
+}
@@ -0,0 +1,21 @@
+use super::Displayable;
+use sdl2::rect::Point;
+use crate::Mode;
+use crate::Shape;
+use crate::World;
+pub mod button;
+pub mod message;
+pub mod overlay;
+pub mod storage;
+
+use message::Message;
+use storage::Storage;
+
+pub trait UI: Displayable
+{
+    fn in_bounds(&self, click: Point) -> bool;
+
+    //Some when the click did something the user should be told about
+    fn click_down(&mut self, state: &mut Mode, world: &mut World, storage: &mut Storage) -> Option<Message>;
+    fn click_up(&mut self, objects: &Vec<Shape>);
+}
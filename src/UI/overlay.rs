@@ -0,0 +1,105 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+
+use super::Displayable;
+use physics_2d::WINDOW_DIMENSIONS;
+use physics_2d::diagnostics::Diagnostics;
+
+use std::collections::VecDeque;
+
+//Steps of history shown, one per pixel across, and the size of each graph
+const HISTORY: usize = 300;
+const HEIGHT: i32 = 120;
+const MARGIN: i32 = 10;
+
+const BORDER: Color = Color::RGB(90, 90, 90);
+const KINETIC: Color = Color::RGB(230, 80, 60);
+const POTENTIAL: Color = Color::RGB(70, 130, 240);
+const TOTAL: Color = Color::RGB(255, 255, 255);
+const MOMENTUM: Color = Color::RGB(60, 220, 90);
+const ANGULAR_MOMENTUM: Color = Color::RGB(240, 210, 60);
+
+//A line on a graph, and how to read its value
+type Series<'a> = (Color, &'a dyn Fn(&Diagnostics) -> f64);
+
+//Graphs of the world's energy and momentum over the last few seconds, in the top right corner.
+//There is no font to write the values with, so they are plotted around a zero line instead. The
+//top graph has kinetic (red), potential (blue) and total (white) energy on one scale, so a
+//conserving world shows a flat white line. The one below has the size of the linear momentum
+//(green) and the angular momentum (yellow), each on its own scale
+#[derive(Debug)]
+pub struct Overlay
+{
+    history: VecDeque<Diagnostics>,
+}
+
+impl Overlay
+{
+    pub fn new() -> Overlay
+    {
+        Overlay{history: VecDeque::new()}
+    }
+
+    pub fn record(&mut self, diagnostics: Diagnostics)
+    {
+        self.history.push_back(diagnostics);
+        if self.history.len() > HISTORY {
+            self.history.pop_front();
+        }
+    }
+
+    //One trace per series, scaled so its largest value just fits, or the largest of the group when shared
+    fn graph(&self, top: i32, series: &[Series], shared: bool, points: &mut Vec<(Point, Color)>)
+    {
+        let left = WINDOW_DIMENSIONS.0 as i32-MARGIN-HISTORY as i32;
+        let right = left+HISTORY as i32;
+        for x in left..right {
+            points.push((Point::new(x, top), BORDER));
+            points.push((Point::new(x, top+HEIGHT/2), BORDER));
+            points.push((Point::new(x, top+HEIGHT), BORDER));
+        }
+        for y in top..=top+HEIGHT {
+            points.push((Point::new(left, y), BORDER));
+            points.push((Point::new(right, y), BORDER));
+        }
+
+        let largest = |value: &dyn Fn(&Diagnostics) -> f64| self.history.iter().map(|d| value(d).abs()).fold(0.0, f64::max);
+        let common = series.iter().map(|(_, value)| largest(*value)).fold(0.0, f64::max);
+        for (color, value) in series {
+            let scale = if shared { common } else { largest(*value) };
+            if scale == 0.0 {
+                continue;
+            }
+            for (i, diagnostics) in self.history.iter().enumerate() {
+                let y = top+HEIGHT/2-(value(diagnostics)/scale*(HEIGHT/2) as f64).round() as i32;
+                points.push((Point::new(left+i as i32, y), *color));
+            }
+        }
+    }
+}
+
+impl Default for Overlay
+{
+    fn default() -> Overlay
+    {
+        Overlay::new()
+    }
+}
+
+impl Displayable for Overlay
+{
+    fn display(&self) -> Vec<(Point, Color)>
+    {
+        let mut points = Vec::new();
+        self.graph(MARGIN, &[
+            (KINETIC, &|d: &Diagnostics| d.kinetic),
+            (POTENTIAL, &|d: &Diagnostics| d.potential),
+            (TOTAL, &|d: &Diagnostics| d.total()),
+        ], true, &mut points);
+        self.graph(2*MARGIN+HEIGHT, &[
+            (MOMENTUM, &|d: &Diagnostics| d.momentum.len()),
+            (ANGULAR_MOMENTUM, &|d: &Diagnostics| d.angular_momentum),
+        ], false, &mut points);
+        points
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{ManipMode, Mode};
+use crate::World;
+use super::message::Message;
+
+use physics_2d::scene::Scene;
+
+use std::collections::VecDeque;
+
+//Where the save and load buttons, and their keyboard shortcuts, keep the scene. While a replay
+//plays nothing is written, and loads are answered with the scenes the recording loaded, so playback
+//neither overwrites the file nor depends on what it holds now
+#[derive(Debug)]
+pub struct Storage
+{
+    path: String,
+    //Some while recording: every load since they were last taken, None for one that failed
+    loaded: Option<Vec<Option<Scene>>>,
+    //Some while replaying: the loads the recording made, in order
+    replayed: Option<VecDeque<Option<Scene>>>,
+}
+
+impl Storage
+{
+    pub fn new(path: &str) -> Storage
+    {
+        Storage{path: path.to_string(), loaded: None, replayed: None}
+    }
+
+    #[inline]
+    pub fn path(&self) -> &str
+    {
+        &self.path
+    }
+
+    pub fn setPath(&mut self, path: &str)
+    {
+        self.path = path.to_string();
+    }
+
+    //Keeps what each load read, for the recording to take
+    pub fn record(&mut self)
+    {
+        self.loaded = Some(Vec::new());
+    }
+
+    pub fn replay(&mut self, loads: &[Option<Scene>])
+    {
+        self.replayed = Some(loads.iter().cloned().collect());
+    }
+
+    //The loads since this was last called, in order
+    pub fn takeLoaded(&mut self) -> Vec<Option<Scene>>
+    {
+        self.loaded.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    //Writes the world out as a scene file
+    pub fn save(&mut self, world: &World) -> Message
+    {
+        if self.replayed.is_some() {
+            return Message::failure("Not saving while a replay plays".to_string());
+        }
+        match Scene::from_world(world).save(&self.path) {
+            Ok(()) => Message::success(format!("Saved the scene to {}", self.path)),
+            Err(error) => Message::failure(format!("Could not save: {}", error)),
+        }
+    }
+
+    //Replaces the world with the one in the scene file and pauses, so it can be looked over before
+    //it runs. A file that cannot be loaded leaves the world as it was
+    pub fn load(&mut self, world: &mut World, state: &mut Mode) -> Message
+    {
+        let scene = match &mut self.replayed {
+            Some(replayed) => replayed.pop_front().flatten().ok_or("the recording could not load it either".to_string()),
+            None => Scene::load(&self.path).map_err(|error| error.to_string()),
+        };
+        let built = scene.and_then(|scene| scene.build().map(|built| (scene, built)).map_err(|error| error.to_string()));
+        if let (Some(loaded), None) = (&mut self.loaded, &self.replayed) {
+            loaded.push(built.as_ref().ok().map(|(scene, _)| scene.clone()));
+        }
+        match built {
+            Ok((_, built)) => {
+                *world = built;
+                *state = Mode::Paused(ManipMode::Move);
+                Message::success(format!("Loaded the scene from {}", self.path))
+            },
+            Err(error) => Message::failure(format!("Could not load: {}", error)),
+        }
+    }
+}
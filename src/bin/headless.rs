@@ -0,0 +1,180 @@
+#![allow(non_snake_case)]
+
+//Runs a scene without a window, for batch runs and scripts. Usage:
+//physics-2d-headless <scene.ron> [--steps N | --duration SECONDS] [--dt SECONDS] [--trace] [--deterministic] [--output FILE]
+//    [--export FILE.csv|FILE.jsonl [--every N] [--quantities position,velocity,angular_velocity,kinetic_energy,contacts | --diagnostics]]
+//    [--capture FILE.png|FILE.bmp|FILE.gif [--fps N]]
+//--diagnostics exports the world's momentum and energy instead of rows for each body. --capture
+//draws the world to numbered images, or one animated GIF, at --fps frames of simulated time a second
+
+use physics_2d::DT;
+use physics_2d::scene::Scene;
+use physics_2d::world::World;
+use physics_2d::displayable::physics::Physics;
+use physics_2d::export::{Exporter, Quantity};
+use physics_2d::capture::{Capture, Image};
+use physics_2d::displayable::Displayable;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Write, BufWriter};
+use std::process;
+
+const USAGE: &str = "usage: physics-2d-headless <scene.ron> [--steps N | --duration SECONDS] [--dt SECONDS] [--trace] [--deterministic] [--output FILE] [--export FILE [--every N] [--quantities LIST | --diagnostics]] [--capture FILE [--fps N]]";
+const DEFAULT_STEPS: usize = 60;
+const DEFAULT_FPS: f64 = 30.0;
+
+struct Options
+{
+    scene: String,
+    steps: usize,
+    dt: f64,
+    trace: bool,
+    deterministic: bool,
+    output: Option<String>,
+    export: Option<String>,
+    every: usize,
+    quantities: Vec<Quantity>,
+    diagnostics: bool,
+    capture: Option<String>,
+    fps: f64,
+}
+
+fn parseArgs(args: &[String]) -> Result<Options, String>
+{
+    let mut scene = None;
+    let (mut steps, mut duration) = (None, None);
+    let mut dt = DT;
+    let mut trace = false;
+    let mut deterministic = false;
+    let mut output = None;
+    let mut export = None;
+    let mut every = None;
+    let mut quantities = None;
+    let mut diagnostics = false;
+    let mut capture = None;
+    let mut fps = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().cloned().ok_or(format!("{} needs a value", name));
+        match arg.as_str() {
+            "--steps" => steps = Some(value("--steps")?.parse::<usize>().map_err(|e| format!("bad --steps: {}", e))?),
+            "--duration" => duration = Some(value("--duration")?.parse::<f64>().map_err(|e| format!("bad --duration: {}", e))?),
+            "--dt" => dt = value("--dt")?.parse::<f64>().map_err(|e| format!("bad --dt: {}", e))?,
+            "--trace" => trace = true,
+            "--deterministic" => deterministic = true,
+            "--output" => output = Some(value("--output")?),
+            "--diagnostics" => diagnostics = true,
+            "--capture" => capture = Some(value("--capture")?),
+            "--fps" => fps = Some(value("--fps")?.parse::<f64>().map_err(|e| format!("bad --fps: {}", e))?),
+            "--export" => export = Some(value("--export")?),
+            "--every" => every = Some(value("--every")?.parse::<usize>().map_err(|e| format!("bad --every: {}", e))?),
+            "--quantities" => quantities = Some(value("--quantities")?.split(',').map(|name| name.trim().parse::<Quantity>()).collect::<Result<Vec<_>, _>>()?),
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path if scene.is_none() => scene = Some(path.to_string()),
+            extra => return Err(format!("unexpected argument {}", extra)),
+        }
+    }
+
+    if dt <= 0.0 {
+        return Err("--dt must be positive".to_string());
+    }
+    let steps = match (steps, duration) {
+        (Some(_), Some(_)) => return Err("give --steps or --duration, not both".to_string()),
+        (Some(steps), None) => steps,
+        (None, Some(duration)) => (duration/dt).round() as usize,
+        (None, None) => DEFAULT_STEPS,
+    };
+    if every == Some(0) {
+        return Err("--every must be at least 1".to_string());
+    }
+    if export.is_none() && (every.is_some() || quantities.is_some() || diagnostics) {
+        return Err("--every, --quantities and --diagnostics need --export".to_string());
+    }
+    if capture.is_none() && fps.is_some() {
+        return Err("--fps needs --capture".to_string());
+    }
+    if fps.is_some_and(|fps| fps <= 0.0) {
+        return Err("--fps must be positive".to_string());
+    }
+    if diagnostics && quantities.is_some() {
+        return Err("give --quantities or --diagnostics, not both".to_string());
+    }
+    let scene = scene.ok_or("no scene given".to_string())?;
+    Ok(Options{scene, steps, dt, trace, deterministic, output, export, every: every.unwrap_or(1), quantities: quantities.unwrap_or(Quantity::ALL.to_vec()), diagnostics, capture, fps: fps.unwrap_or(DEFAULT_FPS)})
+}
+
+//One line per body: step, time, id, position, velocity and angular velocity
+fn writeState(out: &mut impl Write, step: usize, time: f64, world: &World) -> io::Result<()>
+{
+    for object in &world.objects {
+        let (position, velocity) = (object.position(), object.velocity());
+        writeln!(out, "{} {} {} {} {} {} {} {}", step, time, object.body().id, position.x(), position.y(), velocity.x(), velocity.y(), object.angular_velocity())?;
+    }
+    Ok(())
+}
+
+fn run(options: &Options) -> Result<(), Box<dyn std::error::Error>>
+{
+    let mut world = Scene::load(&options.scene)?.build()?;
+    world.settings.deterministic |= options.deterministic;
+    let mut out: Box<dyn Write> = match &options.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+
+    let mut exporter = match &options.export {
+        Some(path) => {
+            let exporter = Exporter::create(path)?.with_interval(options.every).with_quantities(options.quantities.clone());
+            Some(if options.diagnostics { exporter.with_diagnostics() } else { exporter })
+        },
+        None => None,
+    };
+
+    let mut capture = match &options.capture {
+        Some(path) => Some(Capture::create(path, options.fps)?),
+        None => None,
+    };
+
+    writeln!(out, "# step time id x y vx vy angular_velocity")?;
+    for step in 1..=options.steps {
+        world.step(options.dt);
+        if let Some(exporter) = &mut exporter {
+            exporter.sample(&world, options.dt)?;
+        }
+        if let Some(capture) = &mut capture {
+            capture.offer(options.dt, || Image::from_points(&world.display()))?;
+        }
+        if options.trace {
+            writeState(&mut out, step, step as f64*options.dt, &world)?;
+        }
+    }
+    writeln!(out, "# final")?;
+    writeState(&mut out, options.steps, options.steps as f64*options.dt, &world)?;
+    writeln!(out, "# checksum {:016x}", world.checksum())?;
+    out.flush()?;
+    if let Some(exporter) = &mut exporter {
+        exporter.flush()?;
+    }
+    if let Some(capture) = capture {
+        capture.finish()?;
+    }
+    Ok(())
+}
+
+fn main()
+{
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parseArgs(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        },
+    };
+    if let Err(error) = run(&options) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
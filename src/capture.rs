@@ -0,0 +1,255 @@
+use crate::WINDOW_DIMENSIONS;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+//A frame drawn off screen, 8 bit RGB, row by row from the top left
+#[derive(Debug, Clone)]
+pub struct Image
+{
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image
+{
+    //All black, like the window before anything is drawn
+    pub fn new(width: u32, height: u32) -> Image
+    {
+        Image{width, height, pixels: vec![0; (width*height*3) as usize]}
+    }
+
+    //The same points Displayable hands the window, drawn at the window's size. Points outside it
+    //are dropped, as the window would clip them
+    pub fn from_points(points: &[(Point, Color)]) -> Image
+    {
+        let mut image = Image::new(WINDOW_DIMENSIONS.0, WINDOW_DIMENSIONS.1);
+        image.draw(points);
+        image
+    }
+
+    pub fn draw(&mut self, points: &[(Point, Color)])
+    {
+        for (point, color) in points {
+            if point.x() < 0 || point.y() < 0 || point.x() as u32 >= self.width || point.y() as u32 >= self.height {
+                continue;
+            }
+            let i = ((point.y() as u32*self.width+point.x() as u32)*3) as usize;
+            self.pixels[i..i+3].copy_from_slice(&[color.r, color.g, color.b]);
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32
+    {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32
+    {
+        self.height
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color
+    {
+        let i = ((y*self.width+x)*3) as usize;
+        Color::RGB(self.pixels[i], self.pixels[i+1], self.pixels[i+2])
+    }
+
+    pub fn savePng(&self, path: impl AsRef<Path>) -> io::Result<()>
+    {
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&self.pixels).map_err(io::Error::other)
+    }
+
+    pub fn saveBmp(&self, path: impl AsRef<Path>) -> io::Result<()>
+    {
+        let mut image = bmp::Image::new(self.width, self.height);
+        for (x, y) in image.coordinates() {
+            let color = self.pixel(x, y);
+            image.set_pixel(x, y, bmp::Pixel::new(color.r, color.g, color.b));
+        }
+        image.save(path)
+    }
+
+    //Scenes are drawn in a handful of colours, so they usually fit a GIF palette exactly. Anything
+    //busier, like metaball fluid, is quantised
+    fn gifFrame(&self) -> gif::Frame<'static>
+    {
+        let (width, height) = (self.width as u16, self.height as u16);
+        let mut palette: HashMap<[u8; 3], u8> = HashMap::new();
+        let mut indices = Vec::with_capacity((self.width*self.height) as usize);
+        for rgb in self.pixels.chunks_exact(3) {
+            let rgb = [rgb[0], rgb[1], rgb[2]];
+            let next = palette.len();
+            if next == 256 && !palette.contains_key(&rgb) {
+                return gif::Frame::from_rgb_speed(width, height, &self.pixels, GIF_SPEED);
+            }
+            indices.push(*palette.entry(rgb).or_insert(next as u8));
+        }
+        let mut colors = vec![0; palette.len()*3];
+        for (rgb, index) in palette {
+            colors[index as usize*3..index as usize*3+3].copy_from_slice(&rgb);
+        }
+        gif::Frame::from_palette_pixels(width, height, &indices, &colors, None)
+    }
+}
+
+//Quantiser speed for frames with too many colours, 1 is best and 30 fastest
+const GIF_SPEED: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureFormat
+{
+    Png,
+    Bmp,
+    Gif,
+}
+
+impl CaptureFormat
+{
+    pub fn from_path(path: &Path) -> Option<CaptureFormat>
+    {
+        match path.extension()?.to_str()? {
+            "png" => Some(CaptureFormat::Png),
+            "bmp" => Some(CaptureFormat::Bmp),
+            "gif" => Some(CaptureFormat::Gif),
+            _ => None,
+        }
+    }
+}
+
+//Saves frames at a steady rate, whatever rate they are offered at. PNG and BMP go to numbered
+//files next to the path given, so shot.png becomes shot_00000.png, shot_00001.png and so on. A GIF
+//is a single file that loops, finished by finish
+pub struct Capture
+{
+    path: PathBuf,
+    format: CaptureFormat,
+    fps: f64,
+    //Time since the last frame was taken, None until the first
+    elapsed: Option<f64>,
+    frames: usize,
+    gif: Option<gif::Encoder<BufWriter<File>>>,
+}
+
+impl Capture
+{
+    //Picks the format from the extension
+    pub fn create(path: impl AsRef<Path>, fps: f64) -> io::Result<Capture>
+    {
+        let path = path.as_ref();
+        let format = CaptureFormat::from_path(path)
+            .ok_or(io::Error::new(io::ErrorKind::InvalidInput, format!("{} should end in .png, .bmp or .gif", path.display())))?;
+        if fps <= 0.0 || !fps.is_finite() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the frame rate must be positive"));
+        }
+        Ok(Capture{path: path.to_path_buf(), format, fps, elapsed: None, frames: 0, gif: None})
+    }
+
+    #[inline]
+    pub fn frames(&self) -> usize
+    {
+        self.frames
+    }
+
+    //Call once per frame with the time it stood for. Takes the first frame offered, then one
+    //every 1/fps seconds. Rendering is left to the caller, and only done when a frame is taken
+    pub fn offer(&mut self, dt: f64, render: impl FnOnce() -> Image) -> io::Result<bool>
+    {
+        let interval = 1.0/self.fps;
+        match &mut self.elapsed {
+            None => self.elapsed = Some(0.0),
+            Some(elapsed) => {
+                *elapsed += dt;
+                //A little slack, or rates that divide the step evenly would skip frames to rounding
+                if *elapsed < interval-1e-9 {
+                    return Ok(false);
+                }
+                *elapsed -= interval;
+            },
+        }
+        self.add(&render())?;
+        Ok(true)
+    }
+
+    pub fn add(&mut self, image: &Image) -> io::Result<()>
+    {
+        match self.format {
+            CaptureFormat::Png => image.savePng(self.numbered())?,
+            CaptureFormat::Bmp => image.saveBmp(self.numbered())?,
+            CaptureFormat::Gif => {
+                if self.gif.is_none() {
+                    let file = BufWriter::new(File::create(&self.path)?);
+                    let mut encoder = gif::Encoder::new(file, image.width() as u16, image.height() as u16, &[]).map_err(io::Error::other)?;
+                    encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+                    self.gif = Some(encoder);
+                }
+                let mut frame = image.gifFrame();
+                //GIF delays are in hundredths of a second, and most viewers slow anything under 2
+                frame.delay = ((100.0/self.fps).round() as u16).max(2);
+                self.gif.as_mut().unwrap().write_frame(&frame).map_err(io::Error::other)?;
+            },
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    //Writes the end of a GIF. Sequences are already complete on disk. Gives the number of frames
+    pub fn finish(self) -> io::Result<usize>
+    {
+        if let Some(encoder) = self.gif {
+            let mut file = encoder.into_inner()?;
+            io::Write::flush(&mut file)?;
+        }
+        Ok(self.frames)
+    }
+
+    fn numbered(&self) -> PathBuf
+    {
+        let stem = self.path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("frame");
+        let extension = self.path.extension().and_then(|extension| extension.to_str()).unwrap_or("");
+        self.path.with_file_name(format!("{}_{:05}.{}", stem, self.frames, extension))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn captures_at_the_frame_rate()
+    {
+        let dir = std::env::temp_dir().join(format!("physics-2d-capture-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let points = vec![(Point::new(1, 2), Color::RGB(255, 0, 0)), (Point::new(-1, 5), Color::RGB(0, 255, 0))];
+        let image = Image::from_points(&points);
+        assert_eq!(image.pixel(1, 2), Color::RGB(255, 0, 0));
+        assert_eq!(image.pixel(0, 0), Color::RGB(0, 0, 0));
+
+        //Twelve 60th of a second frames at 20 frames a second is four frames
+        for name in ["shot.png", "shot.bmp", "shot.gif"] {
+            let mut capture = Capture::create(dir.join(name), 20.0).unwrap();
+            let mut small = Image::new(8, 8);
+            small.draw(&points);
+            for _ in 0..12 {
+                capture.offer(1.0/60.0, || small.clone()).unwrap();
+            }
+            assert_eq!(capture.finish().unwrap(), 4);
+        }
+        assert!(dir.join("shot_00003.png").exists() && !dir.join("shot_00004.png").exists());
+        assert!(dir.join("shot_00003.bmp").exists());
+        assert!(std::fs::read(dir.join("shot.gif")).unwrap().starts_with(b"GIF89a"));
+        assert!(Capture::create(dir.join("shot.jpg"), 20.0).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,80 @@
+use crate::vec2::Vec2;
+
+use std::hash::Hasher;
+
+const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+//64 bit FNV-1a. Too weak for hash tables facing untrusted keys, but fast and fully specified, so the
+//same state hashes the same on every run and every platform. Numbers are always fed in little endian
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv64
+{
+    hash: u64,
+}
+
+impl Fnv64
+{
+    pub fn new() -> Fnv64
+    {
+        Fnv64{hash: OFFSET_BASIS}
+    }
+
+    pub fn writeU64(&mut self, value: u64)
+    {
+        self.write(&value.to_le_bytes());
+    }
+
+    //Hashes the exact bits, so 0.0 and -0.0 differ and nothing is rounded away
+    pub fn writeF64(&mut self, value: f64)
+    {
+        self.writeU64(value.to_bits());
+    }
+
+    pub fn writeVec2(&mut self, value: Vec2)
+    {
+        self.writeF64(value.x());
+        self.writeF64(value.y());
+    }
+}
+
+impl Default for Fnv64
+{
+    fn default() -> Fnv64
+    {
+        Fnv64::new()
+    }
+}
+
+impl Hasher for Fnv64
+{
+    fn write(&mut self, bytes: &[u8])
+    {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64
+    {
+        self.hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn known_values()
+    {
+        //Reference values from the FNV specification
+        assert_eq!(Fnv64::new().finish(), 0xcbf29ce484222325);
+        let mut hash = Fnv64::new();
+        hash.write(b"a");
+        assert_eq!(hash.finish(), 0xaf63dc4c8601ec8c);
+        let mut hash = Fnv64::new();
+        hash.write(b"foobar");
+        assert_eq!(hash.finish(), 0x85944171f73967e8);
+    }
+}
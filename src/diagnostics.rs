@@ -0,0 +1,138 @@
+use crate::vec2::Vec2;
+use crate::world::World;
+use crate::displayable::physics::Physics;
+
+//Momentum and energy of the world, summed over rigid bodies, soft body points and rope and cloth
+//particles. Fixed bodies and pinned particles never move, so they are left out rather than adding
+//a constant. Fluid and effect particles are not counted, nor is electromagnetic energy.
+//Angular momentum is about the origin, including each body's spin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Diagnostics
+{
+    pub momentum: Vec2,
+    pub angular_momentum: f64,
+    pub kinetic: f64,
+    //From the world's gravity, the springs of soft bodies and n-body attraction
+    pub potential: f64,
+}
+
+impl Diagnostics
+{
+    pub fn new() -> Diagnostics
+    {
+        Diagnostics{momentum: Vec2::zero(), angular_momentum: 0.0, kinetic: 0.0, potential: 0.0}
+    }
+
+    pub fn measure(world: &World) -> Diagnostics
+    {
+        let mut diagnostics = Diagnostics::new();
+        let mut add = |position: Vec2, velocity: Vec2, mass: f64| {
+            let momentum = velocity*mass;
+            diagnostics.momentum += momentum;
+            diagnostics.angular_momentum += position.x()*momentum.y()-position.y()*momentum.x();
+            diagnostics.kinetic += 0.5*mass*velocity.len_squared();
+            diagnostics.potential -= mass*Vec2::dot(&world.gravity, &position);
+        };
+
+        for object in world.objects.iter().filter(|o| !o.body().fixed) {
+            add(object.position(), object.velocity(), object.mass());
+        }
+        for point in world.soft_bodies.iter().flat_map(|body| body.points()) {
+            add(point.position(), point.velocity(), point.mass());
+        }
+        for particle in world.pbd_bodies.iter().flat_map(|body| body.particles()).filter(|p| !p.pinned()) {
+            add(particle.position(), particle.velocity(), particle.mass());
+        }
+
+        for object in world.objects.iter().filter(|o| !o.body().fixed) {
+            let inertia = object.inertia();
+            diagnostics.angular_momentum += inertia*object.angular_velocity();
+            diagnostics.kinetic += 0.5*inertia*object.angular_velocity().powi(2);
+        }
+        diagnostics.potential += world.soft_bodies.iter().map(|body| body.potential()).sum::<f64>();
+        if let Some(nbody) = &world.nbody {
+            diagnostics.potential += nbody.potential(&world.objects);
+        }
+        diagnostics
+    }
+
+    pub fn total(&self) -> f64
+    {
+        self.kinetic+self.potential
+    }
+}
+
+impl Default for Diagnostics
+{
+    fn default() -> Diagnostics
+    {
+        Diagnostics::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+    use crate::displayable::physics::{Shape, circle::Circle, nbody::NBody};
+    use crate::DT;
+
+    fn relative(before: f64, after: f64) -> f64
+    {
+        (after-before).abs()/before.abs().max(1.0)
+    }
+
+    //Pairs of circles of random sizes meeting at random speeds, head on or glancing
+    #[test]
+    fn elastic_collisions_conserve()
+    {
+        let dt = DT;
+        let mut rng = Rng::new(49);
+        for _ in 0..200 {
+            let (r1, r2) = (rng.range(5.0, 50.0), rng.range(5.0, 50.0));
+            let offset = rng.range(-0.9, 0.9)*(r1+r2);
+            let mut world = World::new();
+            world.objects.push(Shape::Circle(Circle::new(Vec2::new(-100.0, 0.0), r1)));
+            world.objects.push(Shape::Circle(Circle::new(Vec2::new(100.0, offset), r2)));
+            //Slow enough that the smallest pair cannot pass through each other in one step
+            world.objects[0].impulse(&Vec2::new(rng.range(50.0, 200.0), 0.0));
+            world.objects[1].impulse(&Vec2::new(-rng.range(50.0, 200.0), 0.0));
+
+            let before = Diagnostics::measure(&world);
+            let mut touched = false;
+            for _ in 0..240 {
+                world.step(dt);
+                touched |= world.contacts().touching().count() > 0;
+            }
+            let after = Diagnostics::measure(&world);
+            assert!(touched);
+            assert!(relative(before.kinetic, after.kinetic) < 1e-9, "{:?} {:?}", before, after);
+            assert!((after.momentum-before.momentum).len() < 1e-6*before.momentum.len().max(1.0), "{:?} {:?}", before, after);
+        }
+    }
+
+    //A pair on a circular orbit. Leapfrog does not conserve energy exactly, but it stays close
+    //instead of drifting away
+    #[test]
+    fn orbits_conserve()
+    {
+        let dt = DT;
+        let mut world = World::new();
+        world.nbody = Some(NBody::new(1000.0));
+        world.objects.push(Shape::Circle(Circle::new_with_mass(Vec2::new(-100.0, 0.0), 5.0, 10000.0)));
+        world.objects.push(Shape::Circle(Circle::new_with_mass(Vec2::new(100.0, 0.0), 5.0, 10000.0)));
+        //Each goes round the centre at the speed gravity alone can hold it to
+        let speed = (1000.0_f64*10000.0/(4.0*100.0)).sqrt();
+        world.objects[0].impulse(&Vec2::new(0.0, -speed));
+        world.objects[1].impulse(&Vec2::new(0.0, speed));
+
+        let before = Diagnostics::measure(&world);
+        for _ in 0..2000 {
+            world.step(dt);
+            let now = Diagnostics::measure(&world);
+            assert!(relative(before.total(), now.total()) < 1e-2, "{:?} {:?}", before, now);
+            assert!((now.momentum-before.momentum).len() < 1e-6, "{:?} {:?}", before, now);
+            assert!(relative(before.angular_momentum, now.angular_momentum) < 1e-9, "{:?} {:?}", before, now);
+        }
+    }
+}
@@ -1,5 +1,4 @@
 pub mod physics;
-pub mod UI;
 
 use sdl2::pixels::Color;
 use sdl2::rect::Point;
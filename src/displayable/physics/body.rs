@@ -0,0 +1,105 @@
+use crate::vec2::Vec2;
+use super::drag::Drag;
+
+use serde::{Serialize, Deserialize};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+//Decides which pairs of bodies collide. Each body is in one or more categories, and only collides
+//with bodies whose categories are in its mask
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CollisionFilter
+{
+    pub category: u16,
+    pub mask: u16,
+    //Bodies sharing a positive group always collide, and sharing a negative group never do,
+    //whatever their categories. 0 means no group
+    pub group: i16,
+}
+
+impl CollisionFilter
+{
+    pub fn new() -> CollisionFilter
+    {
+        CollisionFilter{category: 1, mask: u16::MAX, group: 0}
+    }
+
+    pub fn shouldCollide(&self, other: &CollisionFilter) -> bool
+    {
+        if self.group != 0 && self.group == other.group {
+            return self.group > 0;
+        }
+        self.category & other.mask != 0 && other.category & self.mask != 0
+    }
+}
+
+impl Default for CollisionFilter
+{
+    fn default() -> CollisionFilter
+    {
+        CollisionFilter::new()
+    }
+}
+
+//State every shape carries, whatever its geometry
+#[derive(Debug, Clone, Copy)]
+pub struct Body
+{
+    //Stays the same however the body is moved around the object list. Clones share it
+    pub id: u64,
+    //Fixed bodies never move, whatever hits them. Used for walls and scenery
+    pub fixed: bool,
+    //Sleeping bodies are skipped by integration until something wakes them
+    pub asleep: bool,
+    //How long the body has been moving slowly enough to fall asleep
+    pub rest_time: f64,
+    pub filter: CollisionFilter,
+    //Sensors report what overlaps them but are never collided with
+    pub sensor: bool,
+    //Collisions with a bigger impulse than this shatter the body. None for unbreakable
+    pub break_impulse: Option<f64>,
+    pub charge: f64,
+    //Magnetic dipole moment, pointing from the south pole to the north
+    pub dipole: Vec2,
+    pub drag: Drag,
+}
+
+impl Body
+{
+    pub fn new() -> Body
+    {
+        Body{id: NEXT_ID.fetch_add(1, Ordering::Relaxed), fixed: false, asleep: false, rest_time: 0.0, filter: CollisionFilter::new(), sensor: false, break_impulse: None, charge: 0.0, dipole: Vec2::zero(), drag: Drag::none()}
+    }
+
+    pub fn wake(&mut self)
+    {
+        self.asleep = false;
+        self.rest_time = 0.0;
+    }
+}
+
+impl Default for Body
+{
+    fn default() -> Body
+    {
+        Body::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn filtering()
+    {
+        let scenery = CollisionFilter{category: 0b100, mask: 0, group: 0};
+        let player = CollisionFilter{category: 0b001, mask: 0b011, group: 0};
+        let bullet = CollisionFilter{category: 0b010, mask: 0b011, group: -1};
+        assert!(!scenery.shouldCollide(&player));
+        assert!(player.shouldCollide(&bullet));
+        assert!(!bullet.shouldCollide(&bullet));
+        assert!(CollisionFilter{group: 1, mask: 0, ..player}.shouldCollide(&CollisionFilter{group: 1, mask: 0, ..bullet}));
+    }
+}
@@ -0,0 +1,254 @@
+use crate::vec2::Vec2;
+use super::{Physics, Shape};
+use super::super::Displayable;
+
+use serde::{Serialize, Deserialize};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+
+use bresenham::Bresenham;
+
+//A volume of still fluid. Bodies overlapping it are pushed up in proportion to how much of them is
+//submerged, and slowed down by drag
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FluidRegion
+{
+    //Must be convex and anticlockwise
+    polygon: Vec<Vec2>,
+    density: f64,
+    linear_drag: f64,
+    angular_drag: f64,
+}
+
+impl Displayable for FluidRegion
+{
+    fn display(&self) -> Vec<(Point, Color)>
+    {
+        let extr = |a: Vec2| {let a = Point::from(a); (a.x() as isize, a.y() as isize)};
+
+        let mut points = Vec::new();
+        for (i, a) in self.polygon.iter().enumerate() {
+            let b = self.polygon[(i+1)%self.polygon.len()];
+            points.extend(Bresenham::new(extr(*a), extr(b)).map(|a| (Point::new(a.0 as i32, a.1 as i32), self.color())));
+        }
+        points
+    }
+
+    fn color(&self) -> Color
+    {
+        Color::RGB(60, 120, 255)
+    }
+}
+
+impl FluidRegion
+{
+    pub fn rect(bottomLeft: Vec2, size: Vec2, density: f64) -> FluidRegion
+    {
+        let polygon = vec![
+            bottomLeft,
+            bottomLeft+Vec2::new(size.x(), 0.0),
+            bottomLeft+size,
+            bottomLeft+Vec2::new(0.0, size.y()),
+        ];
+        FluidRegion::polygon(polygon, density)
+    }
+
+    //The polygon must be convex, it is used to clip the bodies
+    pub fn polygon(polygon: Vec<Vec2>, density: f64) -> FluidRegion
+    {
+        assert!(polygon.len() >= 3, "A fluid region needs at least 3 points");
+        let mut polygon = polygon;
+        if area(&polygon) < 0.0 {
+            polygon.reverse();
+        }
+        FluidRegion{polygon, density, linear_drag: 1.0, angular_drag: 1.0}
+    }
+
+    //Drag coefficients are the fraction of velocity lost per second when fully submerged
+    pub fn with_drag(mut self, linear: f64, angular: f64) -> FluidRegion
+    {
+        self.linear_drag = linear;
+        self.angular_drag = angular;
+        self
+    }
+
+    #[inline]
+    pub fn points(&self) -> &[Vec2]
+    {
+        &self.polygon
+    }
+
+    #[inline]
+    pub fn density(&self) -> f64
+    {
+        self.density
+    }
+
+    //The linear and angular drag coefficients
+    #[inline]
+    pub fn drag(&self) -> (f64, f64)
+    {
+        (self.linear_drag, self.angular_drag)
+    }
+
+    //Area of the shape inside the fluid and the centroid of that part, None if it is dry
+    pub fn submerged(&self, shape: &Shape) -> Option<(f64, Vec2)>
+    {
+        let clipped = clip(&shape.polygon(), &self.polygon);
+        let area = area(&clipped);
+        if area <= 0.0 {
+            return None;
+        }
+        Some((area, centroid(&clipped, area)))
+    }
+
+    pub fn apply(&self, objects: &mut [Shape], gravity: &Vec2, dt: f64)
+    {
+        for object in objects.iter_mut() {
+            if object.body().fixed {
+                continue;
+            }
+            if let Some((submerged, centre)) = self.submerged(object) {
+                //Archimedes: the fluid pushes up with the weight of what was displaced, through
+                //the centre of the displaced part
+                let force = *gravity*(-self.density*submerged);
+                let arm = centre-object.position();
+                let torque = arm.x()*force.y()-arm.y()*force.x();
+                object.impulse(&(force*(dt/object.mass())));
+                object.angular_impulse(torque*dt/object.inertia());
+
+                let fraction = submerged/area(&object.polygon());
+                let linear = (self.linear_drag*fraction*dt).min(1.0);
+                let angular = (self.angular_drag*fraction*dt).min(1.0);
+                object.impulse(&(object.velocity()*-linear));
+                object.angular_impulse(-object.angular_velocity()*angular);
+            }
+        }
+    }
+}
+
+//Signed area, positive for anticlockwise polygons
+pub fn area(polygon: &[Vec2]) -> f64
+{
+    let mut area = 0.0;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i+1)%polygon.len()];
+        area += a.x()*b.y()-b.x()*a.y();
+    }
+    area/2.0
+}
+
+//Whether an anticlockwise polygon is convex: it only ever turns left, and goes round exactly once
+pub fn convex(polygon: &[Vec2]) -> bool
+{
+    let mut turning = 0.0;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i+1)%polygon.len()];
+        let c = polygon[(i+2)%polygon.len()];
+        let (edge, next) = (b-*a, c-b);
+        let cross = edge.x()*next.y()-edge.y()*next.x();
+        if cross < 0.0 {
+            return false;
+        }
+        turning += cross.atan2(Vec2::dot(&edge, &next));
+    }
+    (turning-2.0*std::f64::consts::PI).abs() < 1.0e-6
+}
+
+fn centroid(polygon: &[Vec2], area: f64) -> Vec2
+{
+    let mut centre = Vec2::zero();
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i+1)%polygon.len()];
+        let cross = a.x()*b.y()-b.x()*a.y();
+        centre += (*a+b)*cross;
+    }
+    centre/(6.0*area)
+}
+
+//Sutherland-Hodgman: cuts away the parts of subject outside each edge of the convex clip polygon
+pub fn clip(subject: &[Vec2], clip: &[Vec2]) -> Vec<Vec2>
+{
+    let mut output = subject.to_vec();
+    for (i, a) in clip.iter().enumerate() {
+        let b = clip[(i+1)%clip.len()];
+        let edge = b-*a;
+        let inside = |p: &Vec2| edge.x()*(p.y()-a.y())-edge.y()*(p.x()-a.x()) >= 0.0;
+
+        let input = std::mem::take(&mut output);
+        for (j, current) in input.iter().enumerate() {
+            let previous = input[(j+input.len()-1)%input.len()];
+            let crossing = |p: &Vec2, q: &Vec2| {
+                let d1 = edge.x()*(p.y()-a.y())-edge.y()*(p.x()-a.x());
+                let d2 = edge.x()*(q.y()-a.y())-edge.y()*(q.x()-a.x());
+                *p+(*q-*p)*(d1/(d1-d2))
+            };
+            if inside(current) {
+                if !inside(&previous) {
+                    output.push(crossing(&previous, current));
+                }
+                output.push(*current);
+            }
+            else if inside(&previous) {
+                output.push(crossing(&previous, current));
+            }
+        }
+        if output.is_empty() {
+            break;
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+    use crate::displayable::physics::{rect::Rect, circle::Circle};
+    #[test]
+    fn submerged_rect()
+    {
+        let water = FluidRegion::rect(Vec2::new(-100.0, -100.0), Vec2::new(200.0, 100.0), 1.0);
+        let crate_ = Shape::Rect(Rect::from_centre(Vec2::new(0.0, 0.0), Vec2::new(20.0, 40.0), 0.0));
+        let (area, centre) = water.submerged(&crate_).unwrap();
+        assert_approx_eq!(area, 400.0);
+        assert_eq!(centre, Vec2::new(0.0, -10.0));
+    }
+    #[test]
+    fn submerged_circle()
+    {
+        let water = FluidRegion::rect(Vec2::new(-100.0, -100.0), Vec2::new(200.0, 200.0), 1.0);
+        let ball = Shape::Circle(Circle::new(Vec2::new(0.0, 0.0), 10.0));
+        let (area, centre) = water.submerged(&ball).unwrap();
+        assert_approx_eq!(area, std::f64::consts::PI*100.0, 1.0e-6);
+        assert_eq!(centre, Vec2::zero());
+
+        let dry = Shape::Circle(Circle::new(Vec2::new(0.0, 200.0), 10.0));
+        assert!(water.submerged(&dry).is_none());
+    }
+    #[test]
+    fn floats_at_its_draft()
+    {
+        //Given clockwise, which should read the same as anticlockwise. The drag is raised so the
+        //bobbing dies down within the ten seconds run
+        let water = FluidRegion::polygon(vec![Vec2::new(-200.0, -400.0), Vec2::new(-200.0, 0.0), Vec2::new(200.0, 0.0), Vec2::new(200.0, -400.0)], 1.0).with_drag(4.0, 4.0);
+        let settle = |density: f64| {
+            let size = Vec2::new(80.0, 20.0);
+            let mut objects = vec![Shape::Rect(Rect::from_centre_with_mass(Vec2::new(0.0, 30.0), size, 0.0, density*size.x()*size.y()))];
+            let gravity = Vec2::new(0.0, -500.0);
+            let dt = crate::DT;
+            for _ in 0..600 {
+                objects[0].integrate(&gravity, dt);
+                water.apply(&mut objects, &gravity, dt);
+            }
+            objects.remove(0)
+        };
+        //Half as dense as the water, so half of it is under: the bottom at y = -10 and the centre at 0
+        let light = settle(0.5);
+        assert!(light.position().y().abs() < 1.0, "floating at {:?}", light.position());
+        assert!(light.velocity().len() < 1.0);
+        let heavy = settle(2.0);
+        assert!(heavy.position().y() < -100.0, "sinking at {:?}", heavy.position());
+    }
+}
@@ -1,9 +1,5 @@
 use crate::vec2::Vec2;
-use crate::WINDOW_DIMENSIONS;
-use crate::DELTA_TIME;
-use crate::DRAG;
-use crate::ELASTICITY;
-use super::{Physics, Intersect, Shape};
+use super::{Physics, Intersect, Shape, body::Body, sleep::SLEEPING_COLOR, sensor::SENSOR_COLOR, contact::contactNormal};
 
 use sdl2::rect::Point;
 use sdl2::pixels::Color;
@@ -18,13 +14,14 @@ pub struct Circle
     velocity: Vec2,
     angular_velocity: f64,
     mass: f64,
+    body: Body,
 }
 
 impl Displayable for Circle
 {
     fn display(&self) -> Vec<(Point, Color)>
     {
-        let r2 = self.radius.powf(2_f64);
+        let r2 = self.radius.powi(2);
         
         //Computes how far away the centre of a pixel would be from the idealised point on the circle
         //The point given is relative to the centre of the circle
@@ -54,9 +51,28 @@ impl Displayable for Circle
             .map(|x| (Point::from(x), self.color())).collect();
         points
     }
+
+    fn color(&self) -> Color
+    {
+        match self.body {
+            Body{sensor: true, ..} => SENSOR_COLOR,
+            Body{asleep: true, ..} => SLEEPING_COLOR,
+            _ => Color::RGB(255, 255, 255),
+        }
+    }
 }
 impl Physics for Circle
 {
+    #[inline]
+    fn body(&self) -> &Body
+    {
+        &self.body
+    }
+    #[inline]
+    fn mutBody(&mut self) -> &mut Body
+    {
+        &mut self.body
+    }
     #[inline]
     fn position(&self) -> Vec2
     {
@@ -85,23 +101,33 @@ impl Physics for Circle
     #[inline]
     fn impulse(&mut self, impulse: &Vec2)
     {
-        self.velocity += *impulse;
+        if !self.body.fixed {
+            self.body.wake();
+            self.velocity += *impulse;
+        }
     }
     #[inline]
     fn angular_impulse(&mut self, impulse: f64)
     {
-        self.angular_velocity += impulse;
+        if !self.body.fixed {
+            self.body.wake();
+            self.angular_velocity += impulse;
+        }
     }
     #[inline]
-    fn integrate(&mut self, gravity: &Vec2)
+    fn integrate(&mut self, gravity: &Vec2, dt: f64)
     {
-        self.centre += self.velocity*DELTA_TIME.as_millis() as f64/1000.0;
-        self.velocity += *gravity*DELTA_TIME.as_millis() as f64/1000.0;
-        self.velocity = self.velocity * DRAG;
+        if self.body.fixed || self.body.asleep {
+            return;
+        }
+        //Velocity first, so a ball the contacts stop ends the step at rest rather than a step of gravity
+        //short of it, which would keep it from ever sleeping on the floor
+        self.velocity += *gravity*dt;
+        self.centre += self.velocity*dt;
     }
     fn pointIn(&self, point: &Vec2) -> bool
     {
-        (*point-self.centre).len_squared() <= self.radius.powf(2.0_f64)
+        (*point-self.centre).len_squared() <= self.radius.powi(2)
     }
     fn resize(&mut self, point: &Vec2, newPoint: &Vec2, archive: &Self) {
         self.radius = (archive.radius - point.len()) + newPoint.len();
@@ -109,7 +135,10 @@ impl Physics for Circle
     //Empty since a sphere cannot be rotated
     fn rotate(&mut self, _: &Vec2, _: &Vec2, _: &Self) {}
 
-    fn bounce(&mut self, other: &Shape) {
+    fn bounce(&mut self, other: &Shape, restitution: f64) {
+        if self.body.fixed {
+            return;
+        }
         match other {
             Shape::Circle(circle) => {
                 let v1 = self.velocity().len();
@@ -126,37 +155,17 @@ impl Physics for Circle
                 self.velocity = Vec2::new(x, y);
             },
 
-            Shape::Rect(rect) => {
-                let left = -(WINDOW_DIMENSIONS.0 as f64)/2.0+self.radius()+1.0;
-                let right = -left;
-                let bottom = -(WINDOW_DIMENSIONS.1 as f64)/2.0+self.radius()+1.0;
-                let top = -bottom;
-                let mut new = self.clone();
-                new.integrate(&Vec2::zero());
-                match (new.centre().x(), new.centre().y()) {
-                    (x, y) if x < left => {
-                        //self.centre = Vec2::new(left+1.0, y);
-                        self.velocity = Vec2::new(self.velocity().x().abs(), self.velocity.y());
-                    },
-                    (x, y) if x > right => {
-                        //self.centre = Vec2::new(right, y);
-                        self.velocity = Vec2::new(-(self.velocity().x().abs()), self.velocity.y());
-                    },
-                    (x, y) if y < bottom => {
-                        //self.centre = Vec2::new(x, bottom);
-                        self.velocity = Vec2::new(self.velocity().x(), self.velocity.y().abs());
-                    },
-                    (x, y) if y > top => {
-                        //self.centre = Vec2::new(x, top);
-                        self.velocity = Vec2::new(self.velocity().x(), -(self.velocity.y().abs()));
-                    },
-                    _ => (),
+            Shape::Rect(_) => {
+                //Reflects off the face that was hit, relative to the rect's own motion
+                let normal = contactNormal(other, &Shape::Circle(*self));
+                let relative = self.velocity-other.velocity();
+                let approach = Vec2::dot(&relative, &normal);
+                if approach < 0.0 {
+                    self.velocity += normal*(-2.0*approach);
                 }
-                //let angle = (self.position()-rect.position()).polar().1;
-                //let relativeRot = rect.rotation()-angle;
             },
         }
-        self.velocity = self.velocity * ELASTICITY;
+        self.velocity = self.velocity * restitution;
     }
 }
 
@@ -166,7 +175,7 @@ impl Intersect for Circle
     {
         match other {
             Shape::Circle(circle) => {
-                (self.position()-circle.position()).len_squared() < (self.radius+circle.radius).powf(2.0_f64)
+                (self.position()-circle.position()).len_squared() < (self.radius+circle.radius).powi(2)
             }
             Shape::Rect(rect) => {
                 rect.intersect(&Shape::Circle(*self))
@@ -180,13 +189,13 @@ impl Circle
     #[inline]
     pub fn new(centre: Vec2, radius: f64) -> Circle
     {
-        Circle{centre, radius, velocity: Vec2::zero(), angular_velocity: 0.0, mass: std::f64::consts::PI*radius.powf(2_f64)}
+        Circle{centre, radius, velocity: Vec2::zero(), angular_velocity: 0.0, mass: std::f64::consts::PI*radius.powi(2), body: Body::new()}
     }
 
     #[inline]
     pub fn new_with_mass(centre: Vec2, radius: f64, mass: f64) -> Circle
     {
-        Circle{centre, radius, velocity: Vec2::zero(), angular_velocity: 0.0, mass}
+        Circle{centre, radius, velocity: Vec2::zero(), angular_velocity: 0.0, mass, body: Body::new()}
     }
 
     #[inline]
@@ -200,4 +209,25 @@ impl Circle
     {
         self.radius
     }
+
+    //Unlike impulse these leave the body's sleep state alone, for forces like drag that only ever
+    //slow a body down and so should not keep it awake
+    pub fn setVelocity(&mut self, velocity: Vec2, angular_velocity: f64)
+    {
+        self.velocity = velocity;
+        self.angular_velocity = angular_velocity;
+    }
+
+    //If the point is inside the circle, returns the outward normal and how far the point must move
+    //along it to reach the surface
+    pub fn penetration(&self, point: &Vec2) -> Option<(Vec2, f64)>
+    {
+        let relative = *point-self.centre;
+        let distance = relative.len();
+        if distance >= self.radius {
+            return None;
+        }
+        let normal = if distance > 0.0 { relative/distance } else { Vec2::new(0.0, 1.0) };
+        Some((normal, self.radius-distance))
+    }
 }
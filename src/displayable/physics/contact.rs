@@ -0,0 +1,276 @@
+use crate::vec2::Vec2;
+use super::{Physics, Shape, surface::SurfaceListener};
+
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Contact
+{
+    //Indices into the object list for this step, and the bodies' ids, which last between steps
+    pub a: usize,
+    pub b: usize,
+    pub id_a: u64,
+    pub id_b: u64,
+    //Unit vector pointing from a towards b
+    pub normal: Vec2,
+    //A pre-solve listener can switch a contact off, and the bodies pass through each other
+    pub enabled: bool,
+    pub restitution: f64,
+    //Velocity the surfaces appear to move at, which the bodies take on along the contact
+    pub surface_velocity: Vec2,
+}
+
+//Implement whichever callbacks are needed, the rest do nothing
+pub trait ContactListener
+{
+    //Two bodies have started touching
+    fn beginContact(&mut self, _contact: &Contact) {}
+    //Two bodies, given by id, are no longer touching
+    fn endContact(&mut self, _id_a: u64, _id_b: u64) {}
+    //Called before the collision is resolved, and may disable it or change how bouncy it is
+    fn preSolve(&mut self, _contact: &mut Contact, _a: &Shape, _b: &Shape) {}
+    //Called after the collision with the impulse (change in momentum) given to body a
+    fn postSolve(&mut self, _contact: &Contact, _impulse: Vec2) {}
+}
+
+//Unit vector from a towards b at the point they touch
+pub fn contactNormal(a: &Shape, b: &Shape) -> Vec2
+{
+    let direction = match (a, b) {
+        (Shape::Circle(circle), Shape::Rect(rect)) => {
+            let closest = rect.closestPoint(&circle.centre());
+            if closest == circle.centre() {
+                //The centre has gone inside, so the nearest edge says which way is out
+                rect.penetration(&circle.centre()).map(|(normal, _)| normal*-1.0).unwrap_or(b.position()-a.position())
+            }
+            else {
+                closest-circle.centre()
+            }
+        },
+        (Shape::Rect(_), Shape::Circle(_)) => contactNormal(b, a)*-1.0,
+        _ => b.position()-a.position(),
+    };
+    let length = direction.len();
+    if length > 0.0 { direction/length } else { Vec2::new(0.0, 1.0) }
+}
+
+//What a contact manager remembers from one step to the next, apart from its listeners
+#[derive(Debug, Clone)]
+pub struct ContactCache
+{
+    touching: BTreeSet<(u64, u64)>,
+    impulses: Vec<(Contact, Vec2)>,
+}
+
+//Resolves collisions and keeps track of which bodies are touching, telling the listeners about both
+pub struct ContactManager
+{
+    touching: BTreeSet<(u64, u64)>,
+    //Every contact resolved in the last step, with the impulse given to body a
+    impulses: Vec<(Contact, Vec2)>,
+    listeners: Vec<Box<dyn ContactListener>>,
+}
+
+impl ContactManager
+{
+    pub fn new() -> ContactManager
+    {
+        ContactManager{touching: BTreeSet::new(), impulses: Vec::new(), listeners: vec![Box::new(SurfaceListener)]}
+    }
+
+    pub fn addListener(&mut self, listener: Box<dyn ContactListener>)
+    {
+        self.listeners.push(listener);
+    }
+
+    //Ids of every pair of bodies touching as of the last step
+    pub fn touching(&self) -> impl Iterator<Item = &(u64, u64)>
+    {
+        self.touching.iter()
+    }
+
+    #[inline]
+    pub fn impulses(&self) -> &[(Contact, Vec2)]
+    {
+        &self.impulses
+    }
+
+    pub fn cache(&self) -> ContactCache
+    {
+        ContactCache{touching: self.touching.clone(), impulses: self.impulses.clone()}
+    }
+
+    //Picks up from a cached state. The listeners are kept, so they see contacts begin and end
+    //against the restored state rather than the one they last saw
+    pub fn restoreCache(&mut self, cache: ContactCache)
+    {
+        self.touching = cache.touching;
+        self.impulses = cache.impulses;
+    }
+
+    //The intersecting pairs are found after the step. Colliding bodies are wound back to their
+    //state before it, held in archive, and bounced off each other from there. Bodies in disabled
+    //contacts keep moving as if nothing was hit. Restitution is the default before any pre-solve
+    pub fn solve(&mut self, objects: &mut [Shape], mut archive: Vec<Shape>, intersecting: &[(usize, usize)], restitution: f64)
+    {
+        let mut touching = BTreeSet::new();
+        let mut rewind = vec![false; objects.len()];
+        self.impulses.clear();
+        for &(i1, i2) in intersecting {
+            let (a, b) = archive.split_at_mut(i2);
+            let shape1 = &mut a[i1];
+            let shape2 = &mut b[0];
+
+            let mut contact = Contact{
+                a: i1,
+                b: i2,
+                id_a: shape1.body().id,
+                id_b: shape2.body().id,
+                normal: contactNormal(shape1, shape2),
+                enabled: true,
+                restitution,
+                surface_velocity: Vec2::zero(),
+            };
+            let key = (contact.id_a.min(contact.id_b), contact.id_a.max(contact.id_b));
+            touching.insert(key);
+            if !self.touching.contains(&key) {
+                for listener in self.listeners.iter_mut() {
+                    listener.beginContact(&contact);
+                }
+            }
+
+            for listener in self.listeners.iter_mut() {
+                listener.preSolve(&mut contact, shape1, shape2);
+            }
+            if !contact.enabled {
+                continue;
+            }
+            rewind[i1] = true;
+            rewind[i2] = true;
+
+            let (before1, before2) = (shape1.velocity(), shape2.velocity());
+            let archiveShape1 = shape1.clone();
+            if let Shape::Circle(_) = shape1 {
+                shape1.bounce(shape2, contact.restitution);
+            }
+            if let Shape::Circle(_) = shape2 {
+                shape2.bounce(&archiveShape1, contact.restitution);
+            }
+            if contact.surface_velocity != Vec2::zero() {
+                let tangent = contact.normal.perpendicular();
+                for shape in [&mut *shape1, &mut *shape2] {
+                    if let Shape::Circle(_) = shape {
+                        let slip = Vec2::dot(&(contact.surface_velocity-shape.velocity()), &tangent);
+                        shape.impulse(&(tangent*slip));
+                    }
+                }
+            }
+
+            let change1 = (shape1.velocity()-before1)*shape1.mass();
+            let change2 = (shape2.velocity()-before2)*shape2.mass();
+            let impulse = if change1.len_squared() > 0.0 { change1 } else { change2*-1.0 };
+            for listener in self.listeners.iter_mut() {
+                listener.postSolve(&contact, impulse);
+            }
+            self.impulses.push((contact, impulse));
+        }
+
+        for (a, b) in self.touching.difference(&touching) {
+            for listener in self.listeners.iter_mut() {
+                listener.endContact(*a, *b);
+            }
+        }
+        self.touching = touching;
+
+        for (i, object) in archive.into_iter().enumerate() {
+            if rewind[i] {
+                objects[i] = object;
+            }
+        }
+    }
+}
+
+impl Default for ContactManager
+{
+    fn default() -> ContactManager
+    {
+        ContactManager::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::displayable::physics::{circle::Circle, detectIntersections};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct Recorder(Rc<RefCell<Vec<&'static str>>>);
+
+    impl ContactListener for Recorder
+    {
+        fn beginContact(&mut self, _contact: &Contact) { self.0.borrow_mut().push("begin"); }
+        fn endContact(&mut self, _id_a: u64, _id_b: u64) { self.0.borrow_mut().push("end"); }
+        fn preSolve(&mut self, contact: &mut Contact, _a: &Shape, _b: &Shape)
+        {
+            self.0.borrow_mut().push("pre");
+            contact.enabled = false;
+        }
+        fn postSolve(&mut self, _contact: &Contact, _impulse: Vec2) { self.0.borrow_mut().push("post"); }
+    }
+
+    #[test]
+    fn callbacks()
+    {
+        let mut objects = vec![
+            Shape::Circle(Circle::new(Vec2::zero(), 10.0)),
+            Shape::Circle(Circle::new(Vec2::new(15.0, 0.0), 10.0)),
+        ];
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut contacts = ContactManager::new();
+        contacts.addListener(Box::new(Recorder(log.clone())));
+
+        let (archive, intersecting) = (objects.clone(), detectIntersections(&objects));
+        contacts.solve(&mut objects, archive, &intersecting, 1.0);
+        assert_eq!(*log.borrow(), vec!["begin", "pre"]);
+        assert_eq!(contacts.touching().count(), 1);
+
+        objects[1].translateTo(&Vec2::new(100.0, 0.0));
+        let (archive, intersecting) = (objects.clone(), detectIntersections(&objects));
+        contacts.solve(&mut objects, archive, &intersecting, 1.0);
+        assert_eq!(*log.borrow(), vec!["begin", "pre", "end"]);
+        assert_eq!(contacts.touching().count(), 0);
+    }
+
+    #[test]
+    fn disabled_contacts_pass_through()
+    {
+        //Closing on each other, already overlapping, with every contact switched off
+        let mut objects = vec![
+            Shape::Circle(Circle::new(Vec2::zero(), 10.0)),
+            Shape::Circle(Circle::new(Vec2::new(15.0, 0.0), 10.0)),
+        ];
+        objects[0].impulse(&Vec2::new(60.0, 0.0));
+        objects[1].impulse(&Vec2::new(-60.0, 0.0));
+        let mut contacts = ContactManager::new();
+        contacts.addListener(Box::new(Recorder(Rc::new(RefCell::new(Vec::new())))));
+
+        let dt = crate::DT;
+        for _ in 0..5 {
+            let before: Vec<Vec2> = objects.iter().map(|o| o.position()).collect();
+            let archive = objects.clone();
+            for object in objects.iter_mut() {
+                object.integrate(&Vec2::zero(), dt);
+            }
+            let intersecting = detectIntersections(&objects);
+            assert_eq!(intersecting, vec![(0, 1)]);
+            contacts.solve(&mut objects, archive, &intersecting, 1.0);
+
+            //Neither is wound back or bounced
+            assert!(objects[0].position().x() > before[0].x() && objects[1].position().x() < before[1].x());
+            assert_eq!(objects[0].velocity(), Vec2::new(60.0, 0.0));
+            assert_eq!(objects[1].velocity(), Vec2::new(-60.0, 0.0));
+        }
+        assert!(contacts.impulses().is_empty());
+    }
+}
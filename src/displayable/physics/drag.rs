@@ -0,0 +1,214 @@
+use crate::vec2::Vec2;
+use super::{Physics, Shape};
+
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DragModel
+{
+    None,
+    //Stokes drag, proportional to speed. Suits small, slow bodies in thick fluids
+    Linear(f64),
+    //Newtonian drag, proportional to speed squared. The coefficient is the drag coefficient
+    Quadratic(f64),
+}
+
+//How the air slows a body down. Forces scale with the width the body presents to the flow, so a
+//plate falls slower flat than edge on
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Drag
+{
+    model: DragModel,
+    //Spin decays by this fraction per second, roughly
+    angular: f64,
+    //Lift and turning coefficient for rects flying at an angle, 0 for none
+    lift: f64,
+}
+
+impl Drag
+{
+    pub fn none() -> Drag
+    {
+        Drag{model: DragModel::None, angular: 0.0, lift: 0.0}
+    }
+
+    pub fn linear(coefficient: f64) -> Drag
+    {
+        Drag{model: DragModel::Linear(coefficient), ..Drag::none()}
+    }
+
+    pub fn quadratic(coefficient: f64) -> Drag
+    {
+        Drag{model: DragModel::Quadratic(coefficient), ..Drag::none()}
+    }
+
+    pub fn with_angular(mut self, angular: f64) -> Drag
+    {
+        self.angular = angular;
+        self
+    }
+
+    pub fn with_lift(mut self, lift: f64) -> Drag
+    {
+        self.lift = lift;
+        self
+    }
+
+    #[inline]
+    pub fn model(&self) -> DragModel
+    {
+        self.model
+    }
+
+    //Slows the body down over one step. Each term is solved exactly for a fixed direction of
+    //motion, so the result does not depend on the step size the way a per frame multiplier does.
+    //The body is not woken, so drag alone never stops it falling asleep
+    pub fn apply(&self, object: &mut Shape, dt: f64, air_density: f64)
+    {
+        if object.body().fixed || object.body().asleep {
+            return;
+        }
+        if self.model == DragModel::None && self.lift == 0.0 && self.angular == 0.0 {
+            return;
+        }
+        let (before, beforeSpin) = (object.velocity(), object.angular_velocity());
+        let (mut velocity, mut spin) = (before, beforeSpin);
+        let speed = velocity.len();
+        let mass = object.mass();
+
+        if speed > 0.0 {
+            let direction = velocity/speed;
+            let width = projectedWidth(object, &direction);
+            velocity = match self.model {
+                DragModel::None => velocity,
+                DragModel::Linear(c) => velocity*(-c*width/mass*dt).exp(),
+                DragModel::Quadratic(c) => velocity/(1.0+0.5*air_density*c*width/mass*speed*dt),
+            };
+
+            //A tilted plate is pushed towards its lower edge and turned to lie across the flow,
+            //which together make it swing from side to side as it falls
+            if let (Shape::Rect(rect), true) = (&*object, self.lift != 0.0) {
+                let size = rect.size();
+                let (length, axis) = if size.x().abs() >= size.y().abs() {
+                    (size.x().abs(), Vec2::new(1.0, 0.0))
+                }
+                else {
+                    (size.y().abs(), Vec2::new(0.0, 1.0))
+                };
+                let axis = axis.rotate(&Vec2::zero(), rect.rotation());
+                let sin = axis.x()*direction.y()-axis.y()*direction.x();
+                let cos = Vec2::dot(&axis, &direction);
+                let attack = 2.0*sin*cos;
+                let pressure = 0.5*air_density*speed.powi(2);
+
+                let lift = direction.perpendicular()*(-self.lift*pressure*length*attack);
+                let torque = -self.lift*pressure*length.powi(2)/4.0*attack;
+                velocity += lift*(dt/mass);
+                spin += torque/object.inertia()*dt;
+            }
+        }
+
+        if self.angular != 0.0 {
+            spin *= (-self.angular*dt).exp();
+        }
+        if (velocity, spin) != (before, beforeSpin) {
+            object.setVelocity(velocity, spin);
+        }
+    }
+}
+
+//Width of the shape seen looking along the direction of flow
+pub fn projectedWidth(object: &Shape, direction: &Vec2) -> f64
+{
+    match object {
+        Shape::Circle(circle) => 2.0*circle.radius(),
+        Shape::Rect(rect) => {
+            let across = direction.perpendicular().rotate(&Vec2::zero(), -rect.rotation());
+            let size = rect.size();
+            (size.x()*across.x()).abs()+(size.y()*across.y()).abs()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+    use crate::DT;
+    use crate::displayable::physics::{circle::Circle, rect::Rect};
+    #[test]
+    fn plates_fall_slower_flat()
+    {
+        let drag = Drag::quadratic(1.0);
+        let mut flat = Shape::Rect(Rect::from_centre_with_mass(Vec2::zero(), Vec2::new(100.0, 2.0), 0.0, 100.0)).with_drag(drag);
+        let mut edge = Shape::Rect(Rect::from_centre_with_mass(Vec2::zero(), Vec2::new(2.0, 100.0), 0.0, 100.0)).with_drag(drag);
+        assert_approx_eq!(projectedWidth(&flat, &Vec2::new(0.0, -1.0)), 100.0);
+        assert_approx_eq!(projectedWidth(&edge, &Vec2::new(0.0, -1.0)), 2.0);
+
+        let (gravity, dt) = (Vec2::new(0.0, -500.0), DT);
+        for _ in 0..200 {
+            flat.applyDrag(dt, 1.0);
+            flat.integrate(&gravity, dt);
+            edge.applyDrag(dt, 1.0);
+            edge.integrate(&gravity, dt);
+        }
+        assert!(flat.velocity().len() < edge.velocity().len()/2.0);
+    }
+    #[test]
+    fn linear_drag_is_step_independent()
+    {
+        //The same stretch of time in 60 steps, and in four times as many quarter steps
+        let coast = |dt: f64, steps: usize| {
+            let mut ball = Shape::Circle(Circle::new_with_mass(Vec2::zero(), 5.0, 10.0)).with_drag(Drag::linear(0.5));
+            ball.impulse(&Vec2::new(100.0, 0.0));
+            for _ in 0..steps {
+                ball.applyDrag(dt, 1.0);
+            }
+            ball.velocity().x()
+        };
+        let dt = DT;
+        let (coarse, fine) = (coast(dt, 60), coast(dt/4.0, 240));
+        let exact = 100.0*(-0.5*10.0/10.0*dt*60.0).exp();
+        assert_approx_eq!(coarse, exact);
+        assert_approx_eq!(fine, exact);
+        assert_approx_eq!(coarse, fine);
+    }
+    #[test]
+    fn tilted_plates_flutter()
+    {
+        let drag = Drag::quadratic(1.0).with_lift(1.0).with_angular(6.0);
+        let mut plate = Shape::Rect(Rect::from_centre_with_mass(Vec2::zero(), Vec2::new(100.0, 4.0), 0.3, 400.0)).with_drag(drag);
+        let dt = DT;
+        let mut swings = 0;
+        let mut previous = 0.0;
+        for _ in 0..600 {
+            plate.applyDrag(dt, 1.0);
+            plate.integrate(&Vec2::new(0.0, -500.0), dt);
+            let sideways = plate.velocity().x();
+            if sideways*previous < 0.0 {
+                swings += 1;
+            }
+            previous = sideways;
+            if let Shape::Rect(rect) = &plate {
+                assert!(rect.rotation().abs() < 1.0, "plate tumbled");
+            }
+        }
+        assert!(swings >= 6, "only swung {} times", swings);
+    }
+    #[test]
+    fn slow_bodies_still_fall_asleep()
+    {
+        //Drifting at 1 unit a second in empty space, with and without drag
+        for drag in [Drag::none(), Drag::linear(0.5).with_angular(1.0), Drag::quadratic(1.0)] {
+            let mut world = crate::world::World::new();
+            world.gravity = Vec2::zero();
+            let mut ball = Shape::Circle(Circle::new(Vec2::zero(), 10.0)).with_drag(drag);
+            ball.impulse(&Vec2::new(1.0, 0.0));
+            world.objects.push(ball);
+            for _ in 0..60 {
+                world.step(DT);
+            }
+            assert!(world.objects[0].body().asleep, "{:?} kept the ball awake", drag);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use crate::vec2::Vec2;
+use crate::WINDOW_DIMENSIONS;
+use super::{Physics, Shape};
+use super::super::Displayable;
+
+use serde::{Serialize, Deserialize};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+
+use bresenham::Bresenham;
+
+//Field lines leave each charged body this many ways, and each dipole's north half this many ways
+const LINES: usize = 12;
+const LINE_STEP: f64 = 4.0;
+const LINE_STEPS: usize = 500;
+
+//Coulomb forces between charges, forces between magnetic dipoles, and the Lorentz force from a
+//uniform field. Magnetic fields point out of the screen, so B is a scalar and dipoles lie in the plane
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Electromagnetism
+{
+    coulomb: f64,
+    //The magnetic constant over 4 pi, scaling the dipole forces
+    magnetic: f64,
+    electric_field: Vec2,
+    magnetic_field: f64,
+    //Added to distances so that close passes do not fling bodies off at huge speeds
+    softening: f64,
+}
+
+impl Electromagnetism
+{
+    pub fn new(coulomb: f64, magnetic: f64) -> Electromagnetism
+    {
+        Electromagnetism{coulomb, magnetic, electric_field: Vec2::zero(), magnetic_field: 0.0, softening: 10.0}
+    }
+
+    pub fn with_electric_field(mut self, field: Vec2) -> Electromagnetism
+    {
+        self.electric_field = field;
+        self
+    }
+
+    pub fn with_magnetic_field(mut self, field: f64) -> Electromagnetism
+    {
+        self.magnetic_field = field;
+        self
+    }
+
+    pub fn with_softening(mut self, softening: f64) -> Electromagnetism
+    {
+        self.softening = softening;
+        self
+    }
+
+    #[inline]
+    pub fn electricField(&self) -> Vec2
+    {
+        self.electric_field
+    }
+
+    #[inline]
+    pub fn magneticField(&self) -> f64
+    {
+        self.magnetic_field
+    }
+
+    pub fn setElectricField(&mut self, field: Vec2)
+    {
+        self.electric_field = field;
+    }
+
+    pub fn setMagneticField(&mut self, field: f64)
+    {
+        self.magnetic_field = field;
+    }
+
+    //Electric field at a point, from the uniform field and every charged body
+    pub fn electricFieldAt(&self, point: &Vec2, objects: &[Shape]) -> Vec2
+    {
+        objects.iter()
+            .filter(|o| o.body().charge != 0.0)
+            .fold(self.electric_field, |field, o| field+self.coulombField(&o.position(), o.body().charge, point))
+    }
+
+    //In-plane magnetic field at a point from every dipole. The uniform field is out of the plane,
+    //so it is left out
+    pub fn dipoleFieldAt(&self, point: &Vec2, objects: &[Shape]) -> Vec2
+    {
+        objects.iter()
+            .filter(|o| o.body().dipole != Vec2::zero())
+            .fold(Vec2::zero(), |field, o| {
+                let delta = *point-o.position();
+                let r2 = delta.len_squared()+self.softening.powi(2);
+                let direction = delta/r2.sqrt();
+                let moment = o.body().dipole;
+                field+(direction*(3.0*Vec2::dot(&moment, &direction))-moment)*(self.magnetic/(r2*r2.sqrt()))
+            })
+    }
+
+    fn coulombField(&self, source: &Vec2, charge: f64, point: &Vec2) -> Vec2
+    {
+        let delta = *point-*source;
+        let r2 = delta.len_squared()+self.softening.powi(2);
+        delta*(self.coulomb*charge/(r2*r2.sqrt()))
+    }
+
+    //Force on dipole b from dipole a, where delta points from a to b
+    fn dipoleForce(&self, a: &Vec2, b: &Vec2, delta: &Vec2) -> Vec2
+    {
+        let r2 = delta.len_squared()+self.softening.powi(2);
+        let r = *delta/r2.sqrt();
+        let (ar, br) = (Vec2::dot(a, &r), Vec2::dot(b, &r));
+        (*b*ar+*a*br+r*Vec2::dot(a, b)-r*(5.0*ar*br))*(3.0*self.magnetic/(r2*r2))
+    }
+
+    pub fn forces(&self, objects: &[Shape]) -> Vec<Vec2>
+    {
+        let mut forces = vec![Vec2::zero(); objects.len()];
+        for (i, a) in objects.iter().enumerate() {
+            let (qA, mA) = (a.body().charge, a.body().dipole);
+            //Lorentz force, with v x B for B out of the screen
+            let v = a.velocity();
+            forces[i] += (self.electric_field+Vec2::new(v.y(), -v.x())*self.magnetic_field)*qA;
+
+            for (j, b) in objects.iter().enumerate().skip(i+1) {
+                let (qB, mB) = (b.body().charge, b.body().dipole);
+                if qA != 0.0 && qB != 0.0 {
+                    let force = self.coulombField(&a.position(), qA, &b.position())*qB;
+                    forces[j] += force;
+                    forces[i] += force*-1.0;
+                }
+                if mA != Vec2::zero() && mB != Vec2::zero() {
+                    let force = self.dipoleForce(&mA, &mB, &(b.position()-a.position()));
+                    forces[j] += force;
+                    forces[i] += force*-1.0;
+                }
+            }
+        }
+        forces
+    }
+
+    pub fn apply(&self, objects: &mut [Shape], dt: f64)
+    {
+        let forces = self.forces(objects);
+        for (object, force) in objects.iter_mut().zip(forces) {
+            if force != Vec2::zero() {
+                let mass = object.mass();
+                object.impulse(&(force*(dt/mass)));
+            }
+        }
+    }
+
+    //Traces electric field lines out of the charges and magnetic ones out of the dipoles
+    pub fn fieldLines(&self, objects: &[Shape]) -> FieldLines
+    {
+        let mut electric = Vec::new();
+        let mut magnetic = Vec::new();
+        for object in objects {
+            let body = object.body();
+            let reach = match object {
+                Shape::Circle(circle) => circle.radius(),
+                Shape::Rect(rect) => rect.size().len()/2.0,
+            };
+            for n in 0..LINES {
+                let start = object.position()+Vec2::from_polar(reach, std::f64::consts::TAU*n as f64/LINES as f64);
+                if body.charge != 0.0 {
+                    //Lines run from positive to negative, so negative charges are traced backwards
+                    let sign = body.charge.signum();
+                    electric.push(trace(start, objects, |p| self.electricFieldAt(p, objects)*sign));
+                }
+                if body.dipole != Vec2::zero() && Vec2::dot(&(start-object.position()), &body.dipole) > 0.0 {
+                    magnetic.push(trace(start, objects, |p| self.dipoleFieldAt(p, objects)));
+                }
+            }
+        }
+        FieldLines{electric, magnetic}
+    }
+}
+
+impl Default for Electromagnetism
+{
+    //Charges and dipoles that do nothing
+    fn default() -> Electromagnetism
+    {
+        Electromagnetism::new(0.0, 0.0)
+    }
+}
+
+//Follows the field from start until it runs into a body, off the screen or out of steps
+fn trace(start: Vec2, objects: &[Shape], field: impl Fn(&Vec2) -> Vec2) -> Vec<Vec2>
+{
+    let (halfWidth, halfHeight) = (WINDOW_DIMENSIONS.0 as f64/2.0, WINDOW_DIMENSIONS.1 as f64/2.0);
+    let mut line = vec![start];
+    let mut point = start;
+    for _ in 0..LINE_STEPS {
+        let direction = field(&point);
+        let length = direction.len();
+        if length == 0.0 {
+            break;
+        }
+        point += direction*(LINE_STEP/length);
+        line.push(point);
+        let inside = objects.iter().any(|o| o.pointIn(&point));
+        if inside || point.x().abs() > halfWidth || point.y().abs() > halfHeight {
+            break;
+        }
+    }
+    line
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldLines
+{
+    electric: Vec<Vec<Vec2>>,
+    magnetic: Vec<Vec<Vec2>>,
+}
+
+impl Displayable for FieldLines
+{
+    fn display(&self) -> Vec<(Point, Color)>
+    {
+        let extr = |a: Vec2| {let a = Point::from(a); (a.x() as isize, a.y() as isize)};
+        let draw = |lines: &Vec<Vec<Vec2>>, color: Color| {
+            lines.iter()
+                .flat_map(|line| line.windows(2).flat_map(|w| Bresenham::new(extr(w[0]), extr(w[1]))).collect::<Vec<_>>())
+                .map(move |a| (Point::new(a.0 as i32, a.1 as i32), color))
+                .collect::<Vec<_>>()
+        };
+        let mut points = draw(&self.electric, self.color());
+        points.extend(draw(&self.magnetic, Color::RGB(80, 160, 255)));
+        points
+    }
+
+    fn color(&self) -> Color
+    {
+        Color::RGB(255, 230, 90)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+    use crate::DT;
+    use crate::displayable::physics::circle::Circle;
+    #[test]
+    fn like_charges_repel()
+    {
+        let objects = vec![
+            Shape::Circle(Circle::new(Vec2::new(-50.0, 0.0), 5.0)).with_charge(2.0),
+            Shape::Circle(Circle::new(Vec2::new(50.0, 0.0), 5.0)).with_charge(3.0),
+        ];
+        let forces = Electromagnetism::new(1000.0, 0.0).with_softening(0.0).forces(&objects);
+        assert_approx_eq!(forces[1].x(), 1000.0*2.0*3.0/100.0_f64.powf(2.0));
+        assert_eq!(forces[0]+forces[1], Vec2::zero());
+    }
+    #[test]
+    fn charges_circle_in_magnetic_field()
+    {
+        let (mass, charge, field, speed) = (10.0, 2.0, 0.5, 100.0);
+        let mut objects = vec![Shape::Circle(Circle::new_with_mass(Vec2::zero(), 1.0, mass)).with_charge(charge)];
+        objects[0].impulse(&Vec2::new(speed, 0.0));
+        let em = Electromagnetism::new(0.0, 0.0).with_magnetic_field(field);
+
+        //After half a period the particle should be a diameter away, moving backwards
+        let period = std::f64::consts::TAU*mass/(charge*field);
+        let dt = DT;
+        for _ in 0..(period/(2.0*dt)).round() as usize {
+            em.apply(&mut objects, dt);
+            objects[0].integrate(&Vec2::zero(), dt);
+        }
+        let diameter = 2.0*mass*speed/(charge*field);
+        assert!((objects[0].position().y()+diameter).abs() < 0.05*diameter, "ended at {:?}", objects[0].position());
+        assert!(objects[0].velocity().x() < 0.0);
+    }
+}
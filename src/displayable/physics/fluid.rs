@@ -0,0 +1,382 @@
+use crate::vec2::Vec2;
+use super::{Physics, Shape};
+use super::super::Displayable;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+//Pressure waves must not cross more than a fraction of the smoothing radius per step
+const SUBSTEPS: u32 = 4;
+//Metaballs are sampled on a grid this many pixels apart
+const METABALL_STEP: i32 = 2;
+const METABALL_THRESHOLD: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FluidSettings
+{
+    //Mass per unit area the fluid settles at
+    pub rest_density: f64,
+    //How hard the fluid pushes back when compressed
+    pub stiffness: f64,
+    pub viscosity: f64,
+    //Radius over which particles interact
+    pub smoothing: f64,
+}
+
+impl FluidSettings
+{
+    //Same density as the rigid bodies, so a Circle made with Circle::new is neutrally buoyant
+    pub fn water() -> FluidSettings
+    {
+        FluidSettings{rest_density: 1.0, stiffness: 1000000.0, viscosity: 100.0, smoothing: 16.0}
+    }
+
+    //Particles are laid out this far apart so that they start at the rest density
+    #[inline]
+    pub fn spacing(&self) -> f64
+    {
+        self.smoothing/2.0
+    }
+
+    #[inline]
+    pub fn particle_mass(&self) -> f64
+    {
+        self.rest_density*self.spacing().powi(2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FluidRender
+{
+    Points,
+    Metaballs,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FluidParticle
+{
+    position: Vec2,
+    velocity: Vec2,
+    force: Vec2,
+    density: f64,
+    pressure: f64,
+}
+
+impl FluidParticle
+{
+    #[inline]
+    pub fn position(&self) -> Vec2
+    {
+        self.position
+    }
+
+    #[inline]
+    pub fn velocity(&self) -> Vec2
+    {
+        self.velocity
+    }
+
+    #[inline]
+    pub fn density(&self) -> f64
+    {
+        self.density
+    }
+}
+
+//Smoothed-particle hydrodynamics, using the 2D forms of the Muller et al. kernels
+#[derive(Debug, Clone)]
+pub struct Fluid
+{
+    particles: Vec<FluidParticle>,
+    settings: FluidSettings,
+    render: FluidRender,
+    //Particle indices bucketed into cells the size of the smoothing radius
+    grid: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl Displayable for Fluid
+{
+    fn display(&self) -> Vec<(Point, Color)>
+    {
+        match self.render {
+            FluidRender::Points => {
+                let offsets = [(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1)];
+                self.particles.iter()
+                    .flat_map(|p| {
+                        let centre = Point::from(p.position);
+                        offsets.iter().map(move |(x, y)| (centre.offset(*x, *y), self.color()))
+                    })
+                    .collect()
+            },
+            FluidRender::Metaballs => self.metaballs(),
+        }
+    }
+
+    fn color(&self) -> Color
+    {
+        Color::RGB(60, 120, 255)
+    }
+}
+
+impl Fluid
+{
+    pub fn new(settings: FluidSettings, render: FluidRender) -> Fluid
+    {
+        Fluid{particles: Vec::new(), settings, render, grid: HashMap::new()}
+    }
+
+    //Fills a rectangle with particles at rest
+    pub fn addBlock(&mut self, bottomLeft: Vec2, size: Vec2)
+    {
+        let spacing = self.settings.spacing();
+        let cols = (size.x()/spacing) as usize;
+        let rows = (size.y()/spacing) as usize;
+        for row in 0..rows {
+            for col in 0..cols {
+                let position = bottomLeft+Vec2::new((col as f64+0.5)*spacing, (row as f64+0.5)*spacing);
+                self.particles.push(FluidParticle{position, velocity: Vec2::zero(), force: Vec2::zero(), density: 0.0, pressure: 0.0});
+            }
+        }
+    }
+
+    #[inline]
+    pub fn particles(&self) -> &[FluidParticle]
+    {
+        &self.particles
+    }
+
+    #[inline]
+    pub fn settings(&self) -> FluidSettings
+    {
+        self.settings
+    }
+
+    #[inline]
+    pub fn render(&self) -> FluidRender
+    {
+        self.render
+    }
+
+    pub fn setRender(&mut self, render: FluidRender)
+    {
+        self.render = render;
+    }
+
+    pub fn integrate(&mut self, gravity: &Vec2, objects: &mut [Shape], dt: f64)
+    {
+        let dt = dt/SUBSTEPS as f64;
+        for _ in 0..SUBSTEPS {
+            self.buildGrid();
+            self.computeDensities();
+            self.computeForces(gravity);
+            for particle in &mut self.particles {
+                particle.velocity += particle.force*(dt/particle.density);
+                particle.position += particle.velocity*dt;
+            }
+            self.collide(objects);
+        }
+    }
+
+    fn cell(&self, position: &Vec2) -> (i32, i32)
+    {
+        ((position.x()/self.settings.smoothing).floor() as i32, (position.y()/self.settings.smoothing).floor() as i32)
+    }
+
+    fn buildGrid(&mut self)
+    {
+        for cell in self.grid.values_mut() {
+            cell.clear();
+        }
+        for i in 0..self.particles.len() {
+            let cell = self.cell(&self.particles[i].position);
+            self.grid.entry(cell).or_default().push(i);
+        }
+        self.grid.retain(|_, cell| !cell.is_empty());
+    }
+
+    //Calls f with the index of every particle in the 3x3 block of cells around the position
+    fn forNeighbours(&self, position: &Vec2, mut f: impl FnMut(usize))
+    {
+        let (cx, cy) = self.cell(position);
+        for x in cx-1..=cx+1 {
+            for y in cy-1..=cy+1 {
+                if let Some(cell) = self.grid.get(&(x, y)) {
+                    for j in cell {
+                        f(*j);
+                    }
+                }
+            }
+        }
+    }
+
+    fn computeDensities(&mut self)
+    {
+        let h2 = self.settings.smoothing.powi(2);
+        let poly6 = 4.0/(PI*self.settings.smoothing.powf(8.0_f64));
+        let mass = self.settings.particle_mass();
+
+        for i in 0..self.particles.len() {
+            let position = self.particles[i].position;
+            let mut density = 0.0;
+            self.forNeighbours(&position, |j| {
+                let r2 = (self.particles[j].position-position).len_squared();
+                if r2 < h2 {
+                    density += mass*poly6*(h2-r2).powi(3);
+                }
+            });
+            self.particles[i].density = density;
+            self.particles[i].pressure = self.settings.stiffness*(density-self.settings.rest_density);
+        }
+    }
+
+    fn computeForces(&mut self, gravity: &Vec2)
+    {
+        let h = self.settings.smoothing;
+        let spiky = -30.0/(PI*h.powf(5.0_f64));
+        let laplacian = 40.0/(PI*h.powf(5.0_f64));
+        let mass = self.settings.particle_mass();
+
+        for i in 0..self.particles.len() {
+            let particle = self.particles[i];
+            let mut pressure = Vec2::zero();
+            let mut viscosity = Vec2::zero();
+            self.forNeighbours(&particle.position, |j| {
+                if i == j {
+                    return;
+                }
+                let other = self.particles[j];
+                let delta = particle.position-other.position;
+                let r = delta.len();
+                if r < h && r > 0.0 {
+                    let direction = delta/r;
+                    pressure += direction*(-mass*(particle.pressure+other.pressure)/(2.0*other.density)*spiky*(h-r).powi(2));
+                    viscosity += (other.velocity-particle.velocity)*(self.settings.viscosity*mass/other.density*laplacian*(h-r));
+                }
+            });
+            self.particles[i].force = pressure+viscosity+*gravity*particle.density;
+        }
+    }
+
+    //Particles are pushed out of the rigid bodies and lose their approach speed, with the
+    //momentum they lose handed to the body they hit. That exchange is what floats and drags it
+    fn collide(&mut self, objects: &mut [Shape])
+    {
+        let mass = self.settings.particle_mass();
+        for particle in &mut self.particles {
+            for object in objects.iter_mut() {
+                if let Some((normal, depth)) = object.penetration(&particle.position) {
+                    particle.position += normal*depth;
+                    let approach = Vec2::dot(&(particle.velocity-object.velocity()), &normal);
+                    if approach < 0.0 {
+                        let change = normal*(-approach);
+                        particle.velocity += change;
+                        object.impulseAt(&particle.position, &(change*-mass));
+                    }
+                }
+            }
+        }
+    }
+
+    //Sums a falloff around each particle onto a coarse pixel grid and fills the cells over a threshold
+    fn metaballs(&self) -> Vec<(Point, Color)>
+    {
+        let radius = self.settings.spacing();
+        let reach = (radius*2.0) as i32;
+        let mut field: HashMap<(i32, i32), f64> = HashMap::new();
+        for particle in &self.particles {
+            let centre = Point::from(particle.position);
+            let (cx, cy) = (centre.x()-centre.x().rem_euclid(METABALL_STEP), centre.y()-centre.y().rem_euclid(METABALL_STEP));
+            for x in (cx-reach..=cx+reach).step_by(METABALL_STEP as usize) {
+                for y in (cy-reach..=cy+reach).step_by(METABALL_STEP as usize) {
+                    let d2 = ((x-centre.x()).pow(2)+(y-centre.y()).pow(2)) as f64;
+                    let value = (1.0-d2/(4.0*radius*radius)).max(0.0);
+                    *field.entry((x, y)).or_insert(0.0) += value*value;
+                }
+            }
+        }
+
+        let mut points = Vec::new();
+        for ((x, y), value) in field {
+            if value > METABALL_THRESHOLD {
+                for dx in 0..METABALL_STEP {
+                    for dy in 0..METABALL_STEP {
+                        points.push((Point::new(x+dx, y+dy), self.color()));
+                    }
+                }
+            }
+        }
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DT;
+    use crate::displayable::physics::{circle::Circle, rect::Rect};
+    //An open box with a 200 wide floor at y = -100
+    fn tank() -> Vec<Shape>
+    {
+        vec![
+            Shape::Rect(Rect::from_centre(Vec2::new(0.0, -110.0), Vec2::new(240.0, 20.0), 0.0)).fixed(),
+            Shape::Rect(Rect::from_centre(Vec2::new(-110.0, 40.0), Vec2::new(20.0, 300.0), 0.0)).fixed(),
+            Shape::Rect(Rect::from_centre(Vec2::new(110.0, 40.0), Vec2::new(20.0, 300.0), 0.0)).fixed(),
+        ]
+    }
+    #[test]
+    fn settles_at_rest_density()
+    {
+        let mut fluid = Fluid::new(FluidSettings::water(), FluidRender::Points);
+        fluid.addBlock(Vec2::new(-100.0, -100.0), Vec2::new(200.0, 48.0));
+        let mut objects = tank();
+        let dt = DT;
+        for _ in 0..240 {
+            fluid.integrate(&Vec2::new(0.0, -500.0), &mut objects, dt);
+        }
+        //The surface is thinner than the bulk and the bottom a little squeezed, but on the whole it
+        //neither collapses nor flies apart, and stays in the tank
+        let mean = fluid.particles().iter().map(|p| p.density()).sum::<f64>()/fluid.particles().len() as f64;
+        assert!((mean-1.0).abs() < 0.05, "mean density {}", mean);
+        for particle in fluid.particles() {
+            let position = particle.position();
+            assert!(position.x().abs() <= 100.0+1e-9 && position.y() >= -100.0-1e-9 && position.y() < 0.0, "particle at {:?}", position);
+        }
+    }
+    #[test]
+    fn pushes_a_rigid_body()
+    {
+        //A ball a third as dense as water, dropped in at the bottom of the tank. Nothing else holds
+        //it up, so without the fluid it falls straight through the floor
+        let ball = Shape::Circle(Circle::new_with_mass(Vec2::new(0.0, -70.0), 20.0, 400.0));
+        assert!(dropped(ball.clone(), false).y() < -1000.0);
+        let floated = dropped(ball, true);
+        assert!(floated.y() > -60.0 && floated.y() < 40.0, "ball at {:?}", floated);
+    }
+    #[test]
+    fn pushes_a_rigid_rect()
+    {
+        let block = Shape::Rect(Rect::from_centre_with_mass(Vec2::new(0.0, -70.0), Vec2::new(40.0, 40.0), 0.0, 500.0));
+        assert!(dropped(block.clone(), false).y() < -1000.0);
+        let floated = dropped(block, true);
+        assert!(floated.y() > -60.0 && floated.y() < 40.0, "block at {:?}", floated);
+    }
+    //Where a body ends up after three seconds in the tank, with or without water in it
+    fn dropped(body: Shape, fill: bool) -> Vec2
+    {
+        let mut fluid = Fluid::new(FluidSettings::water(), FluidRender::Points);
+        if fill {
+            fluid.addBlock(Vec2::new(-100.0, -100.0), Vec2::new(200.0, 120.0));
+        }
+        let mut objects = tank();
+        objects.push(body);
+        let dt = DT;
+        for _ in 0..180 {
+            objects[3].integrate(&Vec2::new(0.0, -500.0), dt);
+            fluid.integrate(&Vec2::new(0.0, -500.0), &mut objects, dt);
+        }
+        objects[3].position()
+    }
+}
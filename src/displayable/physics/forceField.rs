@@ -0,0 +1,284 @@
+use crate::vec2::Vec2;
+use crate::rng::Rng;
+use super::{Physics, Shape};
+use super::super::Displayable;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+
+use bresenham::Bresenham;
+
+//How quickly bodies in a wind zone are dragged towards the wind's speed, per second
+const WIND_COUPLING: f64 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Falloff
+{
+    Constant,
+    //Full strength at the centre, nothing at the edge of the area
+    Linear,
+    //Strength is the acceleration one unit away from the centre
+    InverseSquare,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum FieldKind
+{
+    //The same acceleration everywhere in the area
+    Uniform(Vec2),
+    //Pulls towards the centre, or pushes away if the strength is negative
+    Attractor{strength: f64, falloff: Falloff},
+    //Swirls anticlockwise around the centre, or clockwise if the strength is negative
+    Vortex{strength: f64, falloff: Falloff},
+    //A single outward kick, strongest at the centre, after which the field is spent
+    Explosion{impulse: f64},
+    //Drags bodies towards the wind's velocity, with random gusts on top
+    Wind{velocity: Vec2, turbulence: f64},
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum FieldArea
+{
+    Circle(f64),
+    //Width and height, centred on the field
+    Rect(Vec2),
+}
+
+#[derive(Debug, Clone)]
+pub struct ForceField
+{
+    position: Vec2,
+    kind: FieldKind,
+    area: FieldArea,
+    spent: bool,
+}
+
+impl Displayable for ForceField
+{
+    fn display(&self) -> Vec<(Point, Color)>
+    {
+        let extr = |a: Vec2| {let a = Point::from(a); (a.x() as isize, a.y() as isize)};
+        let outline: Vec<Vec2> = match self.area {
+            FieldArea::Circle(radius) => {
+                (0..48).map(|i| self.position+Vec2::from_polar(radius, std::f64::consts::TAU*i as f64/48.0)).collect()
+            },
+            FieldArea::Rect(size) => {
+                let half = size/2.0;
+                vec![
+                    self.position-half,
+                    self.position+Vec2::new(half.x(), -half.y()),
+                    self.position+half,
+                    self.position+Vec2::new(-half.x(), half.y()),
+                ]
+            },
+        };
+
+        let mut points = Vec::new();
+        for (i, a) in outline.iter().enumerate() {
+            let b = outline[(i+1)%outline.len()];
+            points.extend(Bresenham::new(extr(*a), extr(b)).map(|a| (Point::new(a.0 as i32, a.1 as i32), self.color())));
+        }
+        let centre = Point::from(self.position);
+        for d in -3..=3 {
+            points.push((centre.offset(d, 0), self.color()));
+            points.push((centre.offset(0, d), self.color()));
+        }
+        points
+    }
+
+    fn color(&self) -> Color
+    {
+        match self.kind {
+            FieldKind::Uniform(_) => Color::RGB(200, 200, 200),
+            FieldKind::Attractor{strength, ..} if strength >= 0.0 => Color::RGB(80, 220, 80),
+            FieldKind::Attractor{..} => Color::RGB(220, 80, 80),
+            FieldKind::Vortex{..} => Color::RGB(180, 80, 220),
+            FieldKind::Explosion{..} => Color::RGB(255, 160, 0),
+            FieldKind::Wind{..} => Color::RGB(80, 200, 220),
+        }
+    }
+}
+
+impl ForceField
+{
+    pub fn new(position: Vec2, kind: FieldKind, area: FieldArea) -> ForceField
+    {
+        ForceField{position, kind, area, spent: false}
+    }
+
+    #[inline]
+    pub fn position(&self) -> Vec2
+    {
+        self.position
+    }
+
+    #[inline]
+    pub fn kind(&self) -> FieldKind
+    {
+        self.kind
+    }
+
+    #[inline]
+    pub fn area(&self) -> FieldArea
+    {
+        self.area
+    }
+
+    pub fn translateTo(&mut self, point: &Vec2)
+    {
+        self.position = *point;
+    }
+
+    //One-shot fields are spent once they have fired, and can be thrown away
+    #[inline]
+    pub fn spent(&self) -> bool
+    {
+        self.spent
+    }
+
+    pub fn pointIn(&self, point: &Vec2) -> bool
+    {
+        let relative = *point-self.position;
+        match self.area {
+            FieldArea::Circle(radius) => relative.len_squared() <= radius.powi(2),
+            FieldArea::Rect(size) => relative.x().abs() <= size.x()/2.0 && relative.y().abs() <= size.y()/2.0,
+        }
+    }
+
+    //Distance from the centre to the edge of the area, used to scale the linear falloff
+    fn reach(&self) -> f64
+    {
+        match self.area {
+            FieldArea::Circle(radius) => radius,
+            FieldArea::Rect(size) => size.len()/2.0,
+        }
+    }
+
+    fn falloff(&self, falloff: Falloff, distance: f64) -> f64
+    {
+        match falloff {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => (1.0-distance/self.reach()).max(0.0),
+            Falloff::InverseSquare => 1.0/distance.max(1.0).powi(2),
+        }
+    }
+
+    //Acceleration felt by a body at the given position moving at the given velocity
+    pub fn acceleration(&self, position: &Vec2, velocity: &Vec2, rng: &mut Rng) -> Vec2
+    {
+        let relative = *position-self.position;
+        let distance = relative.len();
+        let outward = if distance > 0.0 { relative/distance } else { Vec2::zero() };
+        match self.kind {
+            FieldKind::Uniform(acceleration) => acceleration,
+            FieldKind::Attractor{strength, falloff} => outward*(-strength*self.falloff(falloff, distance)),
+            FieldKind::Vortex{strength, falloff} => outward.perpendicular()*(strength*self.falloff(falloff, distance)),
+            FieldKind::Explosion{..} => Vec2::zero(),
+            FieldKind::Wind{velocity: wind, turbulence} => {
+                let gust = Vec2::new(rng.range(-1.0, 1.0), rng.range(-1.0, 1.0))*turbulence;
+                (wind+gust-*velocity)*WIND_COUPLING
+            },
+        }
+    }
+
+    pub fn apply(&mut self, objects: &mut [Shape], rng: &mut Rng, dt: f64)
+    {
+        if self.spent {
+            return;
+        }
+        for object in objects.iter_mut() {
+            if object.body().fixed || !self.pointIn(&object.position()) {
+                continue;
+            }
+            if let FieldKind::Explosion{impulse} = self.kind {
+                let relative = object.position()-self.position;
+                let distance = relative.len();
+                if distance > 0.0 {
+                    object.impulse(&(relative/distance*(impulse*self.falloff(Falloff::Linear, distance))));
+                }
+            }
+            else {
+                let acceleration = self.acceleration(&object.position(), &object.velocity(), rng);
+                object.impulse(&(acceleration*dt));
+            }
+        }
+        if let FieldKind::Explosion{..} = self.kind {
+            self.spent = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+    use crate::displayable::physics::circle::Circle;
+    #[test]
+    fn falloffs()
+    {
+        let mut rng = Rng::new(31);
+        let at = |field: &ForceField, x: f64, rng: &mut Rng| field.acceleration(&Vec2::new(x, 0.0), &Vec2::zero(), rng).x();
+        let area = FieldArea::Circle(200.0);
+
+        //Twice as far is a quarter as strong, pulling inwards
+        let inverse = ForceField::new(Vec2::zero(), FieldKind::Attractor{strength: 100.0, falloff: Falloff::InverseSquare}, area);
+        assert_approx_eq!(at(&inverse, 10.0, &mut rng), -1.0);
+        assert_approx_eq!(at(&inverse, 20.0, &mut rng), -0.25);
+        assert_approx_eq!(at(&inverse, -20.0, &mut rng), 0.25);
+
+        //Falls in a straight line to nothing at the edge
+        let linear = ForceField::new(Vec2::zero(), FieldKind::Attractor{strength: 100.0, falloff: Falloff::Linear}, area);
+        assert_approx_eq!(at(&linear, 50.0, &mut rng), -75.0);
+        assert_approx_eq!(at(&linear, 100.0, &mut rng), -50.0);
+        assert_approx_eq!(at(&linear, 200.0, &mut rng), 0.0);
+
+        //Negative strength pushes, and a vortex acts across the line to the centre
+        let repulsor = ForceField::new(Vec2::zero(), FieldKind::Attractor{strength: -100.0, falloff: Falloff::Constant}, area);
+        assert_approx_eq!(at(&repulsor, 50.0, &mut rng), 100.0);
+        let vortex = ForceField::new(Vec2::zero(), FieldKind::Vortex{strength: 100.0, falloff: Falloff::Constant}, area);
+        let swirl = vortex.acceleration(&Vec2::new(50.0, 0.0), &Vec2::zero(), &mut rng);
+        assert_approx_eq!(swirl.x(), 0.0);
+        assert_approx_eq!(swirl.len(), 100.0);
+    }
+    #[test]
+    fn explosion_fires_once()
+    {
+        let mut rng = Rng::new(31);
+        let mut explosion = ForceField::new(Vec2::zero(), FieldKind::Explosion{impulse: 200.0}, FieldArea::Circle(100.0));
+        let mut objects = vec![Shape::Circle(Circle::new(Vec2::new(50.0, 0.0), 10.0)), Shape::Circle(Circle::new(Vec2::new(0.0, -25.0), 10.0))];
+        assert!(!explosion.spent());
+        explosion.apply(&mut objects, &mut rng, 0.016);
+        assert!(explosion.spent());
+        //Outwards, at the linear falloff's strength for the distance
+        assert_approx_eq!(objects[0].velocity().x(), 100.0);
+        assert_approx_eq!(objects[1].velocity().y(), -150.0);
+
+        explosion.apply(&mut objects, &mut rng, 0.016);
+        assert_approx_eq!(objects[0].velocity().x(), 100.0);
+        assert_approx_eq!(objects[1].velocity().y(), -150.0);
+    }
+    #[test]
+    fn only_acts_inside_its_area()
+    {
+        let mut rng = Rng::new(31);
+        let circle = ForceField::new(Vec2::new(100.0, 0.0), FieldKind::Uniform(Vec2::new(0.0, 10.0)), FieldArea::Circle(50.0));
+        assert!(circle.pointIn(&Vec2::new(150.0, 0.0)));
+        assert!(!circle.pointIn(&Vec2::new(140.0, 40.0)));
+        let mut rect = ForceField::new(Vec2::zero(), FieldKind::Uniform(Vec2::new(0.0, 10.0)), FieldArea::Rect(Vec2::new(100.0, 20.0)));
+        assert!(rect.pointIn(&Vec2::new(-50.0, 10.0)));
+        assert!(!rect.pointIn(&Vec2::new(0.0, 11.0)));
+        assert!(!rect.pointIn(&Vec2::new(51.0, 0.0)));
+
+        //Bodies are inside when their centre is; fixed bodies are never moved
+        let mut objects = vec![
+            Shape::Circle(Circle::new(Vec2::new(40.0, 5.0), 10.0)),
+            Shape::Circle(Circle::new(Vec2::new(40.0, 15.0), 10.0)),
+            Shape::Circle(Circle::new(Vec2::new(0.0, 0.0), 10.0)).fixed(),
+        ];
+        rect.apply(&mut objects, &mut rng, 0.5);
+        assert_approx_eq!(objects[0].velocity().y(), 5.0);
+        assert_approx_eq!(objects[1].velocity().y(), 0.0);
+        assert_approx_eq!(objects[2].velocity().y(), 0.0);
+        assert!(!rect.spent());
+    }
+}
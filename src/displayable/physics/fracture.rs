@@ -0,0 +1,104 @@
+use crate::vec2::Vec2;
+use super::{Physics, Shape, rect::Rect, body::Body, contact::Contact};
+
+//Broken rects split into a grid this many pieces to a side
+const GRID: usize = 2;
+//Pieces narrower than this are too small to break again
+const MIN_PIECE: f64 = 8.0;
+//Left between neighbouring pieces so they do not start off intersecting each other
+const GAP: f64 = 1.0;
+
+//Shatters every breakable body that took a bigger impulse than its threshold in the last step.
+//The pieces go on the end of the object list, so indices from before this are no longer valid
+pub fn fracture(objects: &mut Vec<Shape>, impulses: &[(Contact, Vec2)])
+{
+    let mut broken: Vec<usize> = Vec::new();
+    for (contact, impulse) in impulses {
+        for index in [contact.a, contact.b] {
+            match objects[index].body().break_impulse {
+                Some(threshold) if impulse.len() > threshold && !broken.contains(&index) => broken.push(index),
+                _ => (),
+            }
+        }
+    }
+
+    //Removing from the back keeps the earlier indices pointing at the right bodies
+    broken.sort_unstable();
+    for index in broken.into_iter().rev() {
+        //Only rects know how to split, breakable circles just survive
+        if let Shape::Rect(rect) = objects[index] {
+            objects.remove(index);
+            objects.extend(split(&rect, GRID, GRID));
+        }
+    }
+}
+
+//Cuts a rect into cols*rows equal pieces. Each piece moves with the point of the rect it came
+//from, so the total mass and momentum are the same as before. Fixed rects come loose
+pub fn split(rect: &Rect, cols: usize, rows: usize) -> Vec<Shape>
+{
+    let size = rect.size();
+    let size = Vec2::new(size.x().abs(), size.y().abs());
+    let piece = Vec2::new(size.x()/cols as f64, size.y()/rows as f64);
+    let mass = rect.mass()/(cols*rows) as f64;
+    let centre = rect.position();
+    let velocity = if rect.body().fixed { Vec2::zero() } else { rect.velocity() };
+    let spin = if rect.body().fixed { 0.0 } else { rect.angular_velocity() };
+
+    let mut template = Body{fixed: false, asleep: false, rest_time: 0.0, ..*rect.body()};
+    if piece.x().min(piece.y()) < MIN_PIECE {
+        template.break_impulse = None;
+    }
+
+    let mut pieces = Vec::with_capacity(cols*rows);
+    for row in 0..rows {
+        for col in 0..cols {
+            let offset = Vec2::new((col as f64+0.5)*piece.x(), (row as f64+0.5)*piece.y())-size/2.0;
+            let position = (centre+offset).rotate(&centre, rect.rotation());
+            let inset = Vec2::new((piece.x()-GAP).max(GAP), (piece.y()-GAP).max(GAP));
+            let mut fragment = Rect::from_centre_with_mass(position, inset, rect.rotation(), mass).with_surface(rect.surface());
+            *fragment.mutBody() = Body{id: fragment.body().id, ..template};
+            fragment.impulse(&(velocity+(position-centre).perpendicular()*spin));
+            fragment.angular_impulse(spin);
+            pieces.push(Shape::Rect(fragment));
+        }
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+    use crate::displayable::physics::{circle::Circle, contact::ContactManager, detectIntersections};
+    #[test]
+    fn split_conserves_mass_and_momentum()
+    {
+        let mut rect = Rect::from_centre(Vec2::new(50.0, 20.0), Vec2::new(60.0, 40.0), 0.3);
+        rect.impulse(&Vec2::new(30.0, -10.0));
+        rect.angular_impulse(2.0);
+
+        let pieces = split(&rect, 3, 2);
+        assert_eq!(pieces.len(), 6);
+        let mass: f64 = pieces.iter().map(|p| p.mass()).sum();
+        let momentum = pieces.iter().fold(Vec2::zero(), |total, p| total+p.velocity()*p.mass());
+        assert_approx_eq!(mass, rect.mass());
+        assert_eq!(momentum, rect.velocity()*rect.mass());
+    }
+    #[test]
+    fn hard_hits_shatter()
+    {
+        let pane = Shape::Rect(Rect::from_centre(Vec2::zero(), Vec2::new(100.0, 20.0), 0.0)).fixed().breakable(1000.0);
+        let mut ball = Circle::new(Vec2::new(0.0, 15.0), 10.0);
+        ball.impulse(&Vec2::new(0.0, -100.0));
+        let mut objects = vec![pane, Shape::Circle(ball)];
+        let id = objects[0].body().id;
+
+        let mut contacts = ContactManager::new();
+        let (archive, intersecting) = (objects.clone(), detectIntersections(&objects));
+        contacts.solve(&mut objects, archive, &intersecting, 1.0);
+        fracture(&mut objects, contacts.impulses());
+        assert_eq!(objects.len(), 1+GRID*GRID);
+        assert!(objects.iter().all(|o| o.body().id != id));
+    }
+}
@@ -1,8 +1,23 @@
 use super::{Displayable, Point, Color};
 use crate::vec2::Vec2;
 
+pub mod body;
 pub mod rect;
 pub mod circle;
+pub mod softBody;
+pub mod pbd;
+pub mod particles;
+pub mod fluid;
+pub mod buoyancy;
+pub mod forceField;
+pub mod nbody;
+pub mod sleep;
+pub mod sensor;
+pub mod contact;
+pub mod surface;
+pub mod fracture;
+pub mod electromagnetism;
+pub mod drag;
 
 #[derive(Debug, Clone)]
 pub enum Shape
@@ -22,6 +37,20 @@ impl Displayable for Shape
     }
 }
 impl Physics for Shape {
+    fn body(&self) -> &body::Body
+    {
+        match self {
+            Self::Rect(rect) => rect.body(),
+            Self::Circle(circle) => circle.body(),
+        }
+    }
+    fn mutBody(&mut self) -> &mut body::Body
+    {
+        match self {
+            Self::Rect(rect) => rect.mutBody(),
+            Self::Circle(circle) => circle.mutBody(),
+        }
+    }
     fn position(&self) -> Vec2 
     {
         match self {
@@ -64,11 +93,11 @@ impl Physics for Shape {
             Self::Circle(circle) => circle.angular_impulse(impulse),
         }
     }
-    fn integrate(&mut self, gravity: &Vec2)
+    fn integrate(&mut self, gravity: &Vec2, dt: f64)
     {
         match self {
-            Self::Rect(rect) => rect.integrate(gravity),
-            Self::Circle(circle) => circle.integrate(gravity),
+            Self::Rect(rect) => rect.integrate(gravity, dt),
+            Self::Circle(circle) => circle.integrate(gravity, dt),
         }
     }
     fn pointIn(&self, point: &Vec2) -> bool
@@ -104,14 +133,129 @@ impl Physics for Shape {
             _ => unreachable!(),
         }
     }
-    fn bounce(&mut self, other: &Shape)
+    fn bounce(&mut self, other: &Shape, restitution: f64)
+    {
+        match self {
+            Self::Rect(rect) => rect.bounce(other, restitution),
+            Self::Circle(circle) => circle.bounce(other, restitution),
+        }
+    }
+
+}
+impl Shape {
+    pub fn fixed(mut self) -> Shape
+    {
+        self.mutBody().fixed = true;
+        self
+    }
+
+    pub fn sensor(mut self) -> Shape
+    {
+        self.mutBody().sensor = true;
+        self
+    }
+
+    pub fn breakable(mut self, impulse: f64) -> Shape
+    {
+        self.mutBody().break_impulse = Some(impulse);
+        self
+    }
+
+    pub fn with_charge(mut self, charge: f64) -> Shape
+    {
+        self.mutBody().charge = charge;
+        self
+    }
+
+    pub fn with_dipole(mut self, dipole: Vec2) -> Shape
+    {
+        self.mutBody().dipole = dipole;
+        self
+    }
+
+    pub fn with_drag(mut self, drag: drag::Drag) -> Shape
+    {
+        self.mutBody().drag = drag;
+        self
+    }
+
+    //Sets both velocities without waking the body
+    pub fn setVelocity(&mut self, velocity: Vec2, angular_velocity: f64)
+    {
+        match self {
+            Self::Rect(rect) => rect.setVelocity(velocity, angular_velocity),
+            Self::Circle(circle) => circle.setVelocity(velocity, angular_velocity),
+        }
+    }
+
+    pub fn applyDrag(&mut self, dt: f64, air_density: f64)
+    {
+        let drag = self.body().drag;
+        drag.apply(self, dt, air_density);
+    }
+
+    pub fn with_filter(mut self, filter: body::CollisionFilter) -> Shape
+    {
+        self.mutBody().filter = filter;
+        self
+    }
+
+    //Moment of inertia about the centre of mass
+    pub fn inertia(&self) -> f64
+    {
+        match self {
+            Self::Rect(rect) => {
+                let size = rect.size();
+                rect.mass()*(size.len_squared())/12.0
+            },
+            Self::Circle(circle) => circle.mass()*circle.radius().powi(2)/2.0,
+        }
+    }
+
+    //Hands the body momentum delivered at a point on it, as a push through the centre plus a turn
+    //about it. This is how particles, soft bodies and fluid that hit a body push back on it
+    pub fn impulseAt(&mut self, point: &Vec2, momentum: &Vec2)
+    {
+        if self.body().fixed {
+            return;
+        }
+        let arm = *point-self.position();
+        let torque = arm.x()*momentum.y()-arm.y()*momentum.x();
+        self.impulse(&(*momentum/self.mass()));
+        self.angular_impulse(torque/self.inertia());
+    }
+
+    //Translational plus rotational
+    pub fn kineticEnergy(&self) -> f64
+    {
+        0.5*self.mass()*self.velocity().len_squared() + 0.5*self.inertia()*self.angular_velocity().powi(2)
+    }
+
+    //The outline as a polygon, anticlockwise. Circles become a regular polygon with the same area
+    pub fn polygon(&self) -> Vec<Vec2>
     {
         match self {
-            Self::Rect(rect) => rect.bounce(other),
-            Self::Circle(circle) => circle.bounce(other),
+            Self::Rect(rect) => rect.points().iter().rev().copied().collect(),
+            Self::Circle(circle) => {
+                const SIDES: usize = 32;
+                let step = std::f64::consts::TAU/SIDES as f64;
+                let radius = circle.radius()*(step/step.sin()).sqrt();
+                (0..SIDES).map(|i| circle.centre()+Vec2::from_polar(radius, step*i as f64)).collect()
+            },
         }
     }
 
+    //Sensors never push anything out of themselves
+    pub fn penetration(&self, point: &Vec2) -> Option<(Vec2, f64)>
+    {
+        if self.body().sensor {
+            return None;
+        }
+        match self {
+            Self::Rect(rect) => rect.penetration(point),
+            Self::Circle(circle) => circle.penetration(point),
+        }
+    }
 }
 impl Intersect for Shape {
     fn intersect(&self, other: &Shape) -> bool 
@@ -124,6 +268,8 @@ impl Intersect for Shape {
 }
 
 pub trait Physics: Displayable {
+    fn body(&self) -> &body::Body;
+    fn mutBody(&mut self) -> &mut body::Body;
     fn position(&self) -> Vec2;
     fn translateTo(&mut self, point: &Vec2);
     fn velocity(&self) -> Vec2;
@@ -132,10 +278,10 @@ pub trait Physics: Displayable {
     fn impulse(&mut self, impulse: &Vec2);
     fn angular_impulse(&mut self, impulse: f64);
     fn pointIn(&self, point: &Vec2) -> bool;
-    fn integrate(&mut self, gravity: &Vec2);
+    fn integrate(&mut self, gravity: &Vec2, dt: f64);
     fn resize(&mut self, point: &Vec2, newPoint: &Vec2, archive: &Self);
     fn rotate(&mut self, point: &Vec2, newPoint: &Vec2, archive: &Self);
-    fn bounce(&mut self, other: &Shape);
+    fn bounce(&mut self, other: &Shape, restitution: f64);
 }
 
 pub trait Intersect: Physics {
@@ -143,11 +289,13 @@ pub trait Intersect: Physics {
 }
 
 //Unomptimised, and O(n) in number of objects
+//Pairs whose collision filters exclude each other are skipped before the shapes are tested, as are
+//sensors, which are handled by sensor::Sensors
 pub fn detectIntersections(objects: &Vec<Shape>) -> Vec<(usize, usize)> {
     let mut intersections = Vec::new();
     for (i, o) in objects.iter().enumerate() {
         for (i1, o1) in objects.iter().enumerate() {
-            if i1 > i {
+            if i1 > i && !o.body().sensor && !o1.body().sensor && o.body().filter.shouldCollide(&o1.body().filter) {
                 if o.intersect(o1) {
                     intersections.push((i, i1));
                 }
@@ -0,0 +1,294 @@
+use crate::vec2::Vec2;
+use super::{Physics, Shape};
+
+use serde::{Serialize, Deserialize};
+
+//Quadtree nodes smaller than this stop splitting, so bodies on top of each other share a leaf
+const MAX_DEPTH: u32 = 32;
+
+//Newtonian gravity between every pair of dynamic bodies
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct NBody
+{
+    g: f64,
+    //Added to distances so that close passes do not fling bodies off at huge speeds
+    softening: f64,
+    //Barnes-Hut opening angle. None to sum every pair exactly
+    theta: Option<f64>,
+}
+
+impl NBody
+{
+    pub fn new(g: f64) -> NBody
+    {
+        NBody{g, softening: 1.0, theta: None}
+    }
+
+    //Approximates far away groups of bodies by their centre of mass. Smaller theta is more accurate,
+    //0.5 is typical
+    pub fn with_barnes_hut(mut self, theta: f64) -> NBody
+    {
+        self.theta = Some(theta);
+        self
+    }
+
+    pub fn with_softening(mut self, softening: f64) -> NBody
+    {
+        self.softening = softening;
+        self
+    }
+
+    #[inline]
+    pub fn g(&self) -> f64
+    {
+        self.g
+    }
+
+    #[inline]
+    pub fn barnesHut(&self) -> Option<f64>
+    {
+        self.theta
+    }
+
+    pub fn setBarnesHut(&mut self, theta: Option<f64>)
+    {
+        self.theta = theta;
+    }
+
+    //Gravitational acceleration of every body. Fixed bodies neither pull nor get pulled
+    pub fn accelerations(&self, objects: &[Shape]) -> Vec<Vec2>
+    {
+        match self.theta {
+            None => self.direct(objects),
+            Some(theta) => {
+                let tree = QuadTree::new(objects);
+                objects.iter().enumerate()
+                    .map(|(i, object)| {
+                        if object.body().fixed {
+                            Vec2::zero()
+                        }
+                        else {
+                            tree.acceleration(i, &object.position(), theta, self)
+                        }
+                    })
+                    .collect()
+            },
+        }
+    }
+
+    fn pull(&self, from: &Vec2, to: &Vec2, mass: f64) -> Vec2
+    {
+        let delta = *to-*from;
+        let d2 = delta.len_squared()+self.softening.powi(2);
+        delta*(self.g*mass/(d2*d2.sqrt()))
+    }
+
+    //Gravitational potential energy of every pair, summed exactly whatever theta is, with the same
+    //softening as the pull
+    pub fn potential(&self, objects: &[Shape]) -> f64
+    {
+        let mut potential = 0.0;
+        for (i, a) in objects.iter().enumerate().filter(|(_, o)| !o.body().fixed) {
+            for b in objects.iter().skip(i+1).filter(|o| !o.body().fixed) {
+                let d2 = (a.position()-b.position()).len_squared()+self.softening.powi(2);
+                potential -= self.g*a.mass()*b.mass()/d2.sqrt();
+            }
+        }
+        potential
+    }
+
+    fn direct(&self, objects: &[Shape]) -> Vec<Vec2>
+    {
+        let mut accelerations = vec![Vec2::zero(); objects.len()];
+        for (i, a) in objects.iter().enumerate() {
+            if a.body().fixed {
+                continue;
+            }
+            for (j, b) in objects.iter().enumerate().skip(i+1) {
+                if b.body().fixed {
+                    continue;
+                }
+                accelerations[i] += self.pull(&a.position(), &b.position(), b.mass());
+                accelerations[j] += self.pull(&b.position(), &a.position(), a.mass());
+            }
+        }
+        accelerations
+    }
+
+    //Kick-drift-kick leapfrog. Being symplectic, it keeps orbits closed instead of spiralling
+    //outwards like the explicit step in integrate does
+    pub fn step(&self, objects: &mut [Shape], gravity: &Vec2, dt: f64)
+    {
+        let kick = |objects: &mut [Shape], accelerations: Vec<Vec2>| {
+            for (object, acceleration) in objects.iter_mut().zip(accelerations) {
+                object.impulse(&((acceleration+*gravity)*(dt/2.0)));
+            }
+        };
+
+        kick(objects, self.accelerations(objects));
+        for object in objects.iter_mut() {
+            object.integrate(&Vec2::zero(), dt);
+        }
+        kick(objects, self.accelerations(objects));
+    }
+}
+
+#[derive(Debug)]
+struct Node
+{
+    centre: Vec2,
+    half: f64,
+    mass: f64,
+    //Mass weighted sum of positions until the tree is finished, then the centre of mass
+    centre_of_mass: Vec2,
+    body: Option<usize>,
+    children: Option<[usize; 4]>,
+}
+
+impl Node
+{
+    fn new(centre: Vec2, half: f64) -> Node
+    {
+        Node{centre, half, mass: 0.0, centre_of_mass: Vec2::zero(), body: None, children: None}
+    }
+
+    fn quadrant(&self, point: &Vec2) -> usize
+    {
+        (if point.x() >= self.centre.x() { 1 } else { 0 }) + (if point.y() >= self.centre.y() { 2 } else { 0 })
+    }
+}
+
+struct QuadTree
+{
+    nodes: Vec<Node>,
+}
+
+impl QuadTree
+{
+    fn new(objects: &[Shape]) -> QuadTree
+    {
+        let dynamic: Vec<usize> = (0..objects.len()).filter(|i| !objects[*i].body().fixed).collect();
+
+        let (mut low, mut high) = (Vec2::new(f64::MAX, f64::MAX), Vec2::new(f64::MIN, f64::MIN));
+        for i in &dynamic {
+            let p = objects[*i].position();
+            low = Vec2::new(low.x().min(p.x()), low.y().min(p.y()));
+            high = Vec2::new(high.x().max(p.x()), high.y().max(p.y()));
+        }
+        let half = ((high.x()-low.x()).max(high.y()-low.y())/2.0).max(1.0);
+
+        let mut tree = QuadTree{nodes: vec![Node::new((low+high)/2.0, half)]};
+        for i in dynamic {
+            tree.insert(0, i, objects, 0);
+        }
+        for node in &mut tree.nodes {
+            if node.mass > 0.0 {
+                node.centre_of_mass = node.centre_of_mass/node.mass;
+            }
+        }
+        tree
+    }
+
+    fn insert(&mut self, node: usize, body: usize, objects: &[Shape], depth: u32)
+    {
+        let position = objects[body].position();
+        let mass = objects[body].mass();
+        let empty = self.nodes[node].body.is_none() && self.nodes[node].children.is_none() && self.nodes[node].mass == 0.0;
+        self.nodes[node].mass += mass;
+        self.nodes[node].centre_of_mass += position*mass;
+
+        match self.nodes[node].children {
+            Some(children) => {
+                let child = children[self.nodes[node].quadrant(&position)];
+                self.insert(child, body, objects, depth+1);
+            },
+            None if empty => {
+                self.nodes[node].body = Some(body);
+            },
+            //Too deep to split any further, the leaf just accumulates the extra mass
+            None if depth >= MAX_DEPTH => (),
+            None => {
+                let (centre, half) = (self.nodes[node].centre, self.nodes[node].half/2.0);
+                let mut children = [0; 4];
+                for (quadrant, child) in children.iter_mut().enumerate() {
+                    let x = if quadrant & 1 == 1 { half } else { -half };
+                    let y = if quadrant & 2 == 2 { half } else { -half };
+                    *child = self.nodes.len();
+                    self.nodes.push(Node::new(centre+Vec2::new(x, y), half));
+                }
+                self.nodes[node].children = Some(children);
+
+                let existing = self.nodes[node].body.take();
+                if let Some(existing) = existing {
+                    let p = objects[existing].position();
+                    let child = children[self.nodes[node].quadrant(&p)];
+                    self.insert(child, existing, objects, depth+1);
+                }
+                let child = children[self.nodes[node].quadrant(&position)];
+                self.insert(child, body, objects, depth+1);
+            },
+        }
+    }
+
+    fn acceleration(&self, body: usize, position: &Vec2, theta: f64, nbody: &NBody) -> Vec2
+    {
+        let mut acceleration = Vec2::zero();
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.mass == 0.0 || node.body == Some(body) {
+                continue;
+            }
+            let distance = (node.centre_of_mass-*position).len();
+            match node.children {
+                Some(children) if node.half*2.0 >= theta*distance => stack.extend(children),
+                //Bodies piled exactly on top of this one pull in no particular direction
+                _ if distance == 0.0 => (),
+                _ => acceleration += nbody.pull(position, &node.centre_of_mass, node.mass),
+            }
+        }
+        acceleration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DT;
+    use crate::displayable::physics::circle::Circle;
+    use crate::rng::Rng;
+    #[test]
+    fn circular_orbit_stays_closed()
+    {
+        let (g, mass, radius): (f64, f64, f64) = (1000.0, 1000.0, 100.0);
+        let speed = (g*mass/(4.0*radius)).powf(0.5_f64);
+        let mut objects = vec![
+            Shape::Circle(Circle::new_with_mass(Vec2::new(-radius, 0.0), 5.0, mass)),
+            Shape::Circle(Circle::new_with_mass(Vec2::new(radius, 0.0), 5.0, mass)),
+        ];
+        objects[0].impulse(&Vec2::new(0.0, -speed));
+        objects[1].impulse(&Vec2::new(0.0, speed));
+
+        let nbody = NBody::new(g).with_softening(0.0);
+        for _ in 0..10_000 {
+            nbody.step(&mut objects, &Vec2::zero(), DT);
+            let separation = (objects[0].position()-objects[1].position()).len();
+            assert!((separation-2.0*radius).abs() < 0.01*radius, "separation drifted to {}", separation);
+        }
+        assert_eq!(objects[0].position()+objects[1].position(), Vec2::zero());
+    }
+    #[test]
+    fn barnes_hut_matches_direct()
+    {
+        let mut rng = Rng::new(7);
+        let objects: Vec<Shape> = (0..200)
+            .map(|_| Shape::Circle(Circle::new_with_mass(Vec2::new(rng.range(-400.0, 400.0), rng.range(-400.0, 400.0)), 2.0, rng.range(1.0, 100.0))))
+            .collect();
+
+        let direct = NBody::new(1000.0).accelerations(&objects);
+        let approximate = NBody::new(1000.0).with_barnes_hut(0.3).accelerations(&objects);
+        let error: f64 = direct.iter().zip(&approximate).map(|(exact, estimate)| (*exact-*estimate).len()).sum();
+        let total: f64 = direct.iter().map(|exact| exact.len()).sum();
+        assert!(error < 0.01*total, "mean relative error {}", error/total);
+    }
+}
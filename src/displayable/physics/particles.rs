@@ -0,0 +1,306 @@
+use crate::vec2::Vec2;
+use crate::rng::Rng;
+use super::Shape;
+use super::super::Displayable;
+
+use serde::{Serialize, Deserialize, Serializer, Deserializer};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+
+//Fraction of the normal speed kept when a particle hits a shape
+const RESTITUTION: f64 = 0.3;
+
+//Kept as small as possible, there can be tens of thousands of these
+#[derive(Debug, Clone, Copy)]
+pub struct Particle
+{
+    position: Vec2,
+    velocity: Vec2,
+    age: f64,
+    lifetime: f64,
+    colors: (Color, Color),
+}
+
+impl Particle
+{
+    #[inline]
+    pub fn position(&self) -> Vec2
+    {
+        self.position
+    }
+
+    #[inline]
+    pub fn velocity(&self) -> Vec2
+    {
+        self.velocity
+    }
+
+    //Interpolates from the start colour to the end colour over the particle's life
+    pub fn color(&self) -> Color
+    {
+        let t = (self.age/self.lifetime).clamp(0.0, 1.0);
+        let lerp = |a: u8, b: u8| (a as f64+(b as f64-a as f64)*t) as u8;
+        let (start, end) = self.colors;
+        Color::RGBA(lerp(start.r, end.r), lerp(start.g, end.g), lerp(start.b, end.b), lerp(start.a, end.a))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Emitter
+{
+    position: Vec2,
+    //Angle the particles are fired at, and how far either side of it they may stray
+    direction: f64,
+    spread: f64,
+    //Particles per second
+    rate: f64,
+    speed: (f64, f64),
+    lifetime: (f64, f64),
+    #[serde(with = "colors")]
+    colors: (Color, Color),
+    //Fractional particles carried over between frames
+    #[serde(skip)]
+    accumulator: f64,
+    enabled: bool,
+}
+
+impl Emitter
+{
+    pub fn new(position: Vec2, direction: f64, spread: f64, rate: f64) -> Emitter
+    {
+        Emitter{
+            position,
+            direction,
+            spread,
+            rate,
+            speed: (100.0, 200.0),
+            lifetime: (1.0, 2.0),
+            colors: (Color::RGB(255, 255, 255), Color::RGB(0, 0, 0)),
+            accumulator: 0.0,
+            enabled: true,
+        }
+    }
+
+    pub fn with_speed(mut self, low: f64, high: f64) -> Emitter
+    {
+        self.speed = (low, high);
+        self
+    }
+
+    pub fn with_lifetime(mut self, low: f64, high: f64) -> Emitter
+    {
+        self.lifetime = (low, high);
+        self
+    }
+
+    pub fn with_colors(mut self, start: Color, end: Color) -> Emitter
+    {
+        self.colors = (start, end);
+        self
+    }
+
+    #[inline]
+    pub fn position(&self) -> Vec2
+    {
+        self.position
+    }
+
+    pub fn translateTo(&mut self, point: &Vec2)
+    {
+        self.position = *point;
+    }
+
+    pub fn setEnabled(&mut self, enabled: bool)
+    {
+        self.enabled = enabled;
+    }
+
+    fn emit(&mut self, dt: f64, rng: &mut Rng, particles: &mut Vec<Particle>)
+    {
+        if !self.enabled {
+            return;
+        }
+        self.accumulator += self.rate*dt;
+        while self.accumulator >= 1.0 {
+            self.accumulator -= 1.0;
+            let angle = self.direction+rng.range(-self.spread, self.spread);
+            let speed = rng.range(self.speed.0, self.speed.1);
+            particles.push(Particle{
+                position: self.position,
+                velocity: Vec2::from_polar(speed, angle),
+                age: 0.0,
+                lifetime: rng.range(self.lifetime.0, self.lifetime.1),
+                colors: self.colors,
+            });
+        }
+    }
+}
+
+//Scene files store the start and end colours as (r, g, b) triples
+mod colors
+{
+    use super::*;
+
+    pub fn serialize<S: Serializer>(colors: &(Color, Color), serializer: S) -> Result<S::Ok, S::Error>
+    {
+        let rgb = |c: &Color| (c.r, c.g, c.b);
+        (rgb(&colors.0), rgb(&colors.1)).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(Color, Color), D::Error>
+    {
+        let (start, end): ((u8, u8, u8), (u8, u8, u8)) = Deserialize::deserialize(deserializer)?;
+        Ok((Color::RGB(start.0, start.1, start.2), Color::RGB(end.0, end.1, end.2)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParticleSystem
+{
+    emitters: Vec<Emitter>,
+    particles: Vec<Particle>,
+    rng: Rng,
+    //Whether particles bounce off the rigid bodies. They never push back on them
+    collide: bool,
+}
+
+impl Displayable for ParticleSystem
+{
+    fn display(&self) -> Vec<(Point, Color)>
+    {
+        self.particles.iter().map(|p| (Point::from(p.position), p.color())).collect()
+    }
+}
+
+impl ParticleSystem
+{
+    pub fn new(seed: u64, collide: bool) -> ParticleSystem
+    {
+        ParticleSystem{emitters: Vec::new(), particles: Vec::new(), rng: Rng::new(seed), collide}
+    }
+
+    pub fn addEmitter(&mut self, emitter: Emitter)
+    {
+        self.emitters.push(emitter);
+    }
+
+    #[inline]
+    pub fn emitters(&self) -> &[Emitter]
+    {
+        &self.emitters
+    }
+
+    #[inline]
+    pub fn mutEmitters(&mut self) -> &mut Vec<Emitter>
+    {
+        &mut self.emitters
+    }
+
+    #[inline]
+    pub fn particles(&self) -> &[Particle]
+    {
+        &self.particles
+    }
+
+    pub fn integrate(&mut self, gravity: &Vec2, objects: &[Shape], dt: f64)
+    {
+
+        for emitter in &mut self.emitters {
+            emitter.emit(dt, &mut self.rng, &mut self.particles);
+        }
+
+        for particle in &mut self.particles {
+            particle.age += dt;
+            particle.velocity += *gravity*dt;
+            particle.position += particle.velocity*dt;
+        }
+        self.particles.retain(|p| p.age < p.lifetime);
+
+        if self.collide {
+            for particle in &mut self.particles {
+                for object in objects {
+                    if let Some((normal, depth)) = object.penetration(&particle.position) {
+                        particle.position += normal*depth;
+                        let approach = Vec2::dot(&particle.velocity, &normal);
+                        if approach < 0.0 {
+                            particle.velocity += normal*(-approach*(1.0+RESTITUTION));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DT;
+    #[test]
+    fn emits_at_its_rate()
+    {
+        let mut system = ParticleSystem::new(28, false);
+        system.addEmitter(Emitter::new(Vec2::zero(), 0.0, 0.5, 30.0).with_lifetime(10.0, 10.0));
+        let dt = DT;
+        //125 steps of 16ms is two seconds
+        for _ in 0..125 {
+            system.integrate(&Vec2::zero(), &[], dt);
+        }
+        assert!((59..=60).contains(&system.particles().len()), "{} particles", system.particles().len());
+        for particle in system.particles() {
+            let angle = particle.velocity().y().atan2(particle.velocity().x());
+            assert!(angle.abs() <= 0.5+1e-9);
+            assert!((100.0-1e-9..=200.0+1e-9).contains(&particle.velocity().len()));
+        }
+
+        system.mutEmitters()[0].setEnabled(false);
+        let count = system.particles().len();
+        for _ in 0..60 {
+            system.integrate(&Vec2::zero(), &[], dt);
+        }
+        assert_eq!(system.particles().len(), count);
+    }
+    #[test]
+    fn particles_expire()
+    {
+        let mut system = ParticleSystem::new(28, false);
+        system.addEmitter(Emitter::new(Vec2::zero(), 0.0, 0.0, 100.0).with_lifetime(0.5, 1.0));
+        let dt = DT;
+        for _ in 0..120 {
+            system.integrate(&Vec2::zero(), &[], dt);
+            assert!(system.particles().iter().all(|p| p.age < p.lifetime && p.lifetime >= 0.5 && p.lifetime <= 1.0));
+        }
+        //Nothing lives longer than a second, so all but the youngest have gone after the emitter stops
+        assert!(!system.particles().is_empty());
+        system.mutEmitters()[0].setEnabled(false);
+        for _ in 0..32 {
+            system.integrate(&Vec2::zero(), &[], dt);
+        }
+        let left = system.particles().len();
+        assert!(left > 0 && left < 100, "{} left", left);
+        for _ in 0..32 {
+            system.integrate(&Vec2::zero(), &[], dt);
+        }
+        assert!(system.particles().is_empty());
+    }
+    #[test]
+    fn colour_changes_over_life()
+    {
+        let mut particle = Particle{
+            position: Vec2::zero(),
+            velocity: Vec2::zero(),
+            age: 0.0,
+            lifetime: 2.0,
+            colors: (Color::RGB(255, 0, 0), Color::RGB(0, 0, 255)),
+        };
+        assert_eq!(particle.color(), Color::RGB(255, 0, 0));
+        particle.age = 1.0;
+        assert_eq!(particle.color(), Color::RGB(127, 0, 127));
+        particle.age = 2.0;
+        assert_eq!(particle.color(), Color::RGB(0, 0, 255));
+        //Clamped rather than running past the end colour
+        particle.age = 3.0;
+        assert_eq!(particle.color(), Color::RGB(0, 0, 255));
+    }
+}
@@ -0,0 +1,362 @@
+use crate::vec2::Vec2;
+use super::Shape;
+use super::super::Displayable;
+
+use serde::{Serialize, Deserialize};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+
+use bresenham::Bresenham;
+
+//XPBD converges with a single constraint pass per step as long as the steps are small
+const SUBSTEPS: u32 = 10;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Particle
+{
+    position: Vec2,
+    previous: Vec2,
+    velocity: Vec2,
+    mass: f64,
+    pinned: bool,
+}
+
+impl Particle
+{
+    pub fn new(position: Vec2, mass: f64) -> Particle
+    {
+        Particle{position, previous: position, velocity: Vec2::zero(), mass, pinned: false}
+    }
+
+    #[inline]
+    pub fn position(&self) -> Vec2
+    {
+        self.position
+    }
+
+    #[inline]
+    pub fn velocity(&self) -> Vec2
+    {
+        self.velocity
+    }
+
+    #[inline]
+    pub fn mass(&self) -> f64
+    {
+        self.mass
+    }
+
+    #[inline]
+    pub fn pinned(&self) -> bool
+    {
+        self.pinned
+    }
+
+    #[inline]
+    fn inverseMass(&self) -> f64
+    {
+        if self.pinned { 0.0 } else { 1.0/self.mass }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DistanceConstraint
+{
+    a: usize,
+    b: usize,
+    rest_length: f64,
+    //Inverse stiffness, 0 for a rigid link
+    compliance: f64,
+}
+
+impl DistanceConstraint
+{
+    pub fn ends(&self) -> (usize, usize)
+    {
+        (self.a, self.b)
+    }
+
+    pub fn restLength(&self) -> f64
+    {
+        self.rest_length
+    }
+}
+
+//A rope or a sheet of cloth: particles held together by distance constraints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PbdBody
+{
+    particles: Vec<Particle>,
+    constraints: Vec<DistanceConstraint>,
+}
+
+impl Displayable for PbdBody
+{
+    fn display(&self) -> Vec<(Point, Color)>
+    {
+        let extr = |a: Vec2| {let a = Point::from(a); (a.x() as isize, a.y() as isize)};
+
+        let mut points = Vec::new();
+        for constraint in &self.constraints {
+            let line = Bresenham::new(extr(self.particles[constraint.a].position), extr(self.particles[constraint.b].position))
+                .map(|a| (Point::new(a.0 as i32, a.1 as i32), self.color()));
+            points.extend(line);
+        }
+        points
+    }
+}
+
+impl PbdBody
+{
+    //A chain of segments links from start to end, with its first particle pinned in place
+    pub fn rope(start: Vec2, end: Vec2, segments: usize, mass: f64, compliance: f64) -> PbdBody
+    {
+        assert!(segments >= 1, "A rope needs at least 1 segment");
+
+        let particleMass = mass/(segments+1) as f64;
+        let particles = (0..=segments)
+            .map(|i| Particle::new(start+(end-start)*(i as f64/segments as f64), particleMass))
+            .collect();
+
+        let mut body = PbdBody{particles, constraints: Vec::new()};
+        for i in 0..segments {
+            body.connect(i, i+1, compliance);
+        }
+        body.pin(0);
+        body
+    }
+
+    //A sheet of cols*rows particles hanging down from topLeft, pinned at its two top corners
+    pub fn cloth(topLeft: Vec2, size: Vec2, cols: usize, rows: usize, mass: f64, compliance: f64) -> PbdBody
+    {
+        assert!(cols >= 2 && rows >= 2, "A cloth needs at least 2 particles in each direction");
+
+        let particleMass = mass/(cols*rows) as f64;
+        let spacing = Vec2::new(size.x()/(cols-1) as f64, size.y()/(rows-1) as f64);
+        let index = |col: usize, row: usize| row*cols+col;
+
+        let mut particles = Vec::with_capacity(cols*rows);
+        for row in 0..rows {
+            for col in 0..cols {
+                let position = topLeft+Vec2::new(spacing.x()*col as f64, -spacing.y()*row as f64);
+                particles.push(Particle::new(position, particleMass));
+            }
+        }
+
+        let mut body = PbdBody{particles, constraints: Vec::new()};
+        for row in 0..rows {
+            for col in 0..cols {
+                if col+1 < cols {
+                    body.connect(index(col, row), index(col+1, row), compliance);
+                }
+                if row+1 < rows {
+                    body.connect(index(col, row), index(col, row+1), compliance);
+                }
+            }
+        }
+        body.pin(index(0, 0));
+        body.pin(index(cols-1, 0));
+        body
+    }
+
+    fn connect(&mut self, a: usize, b: usize, compliance: f64)
+    {
+        let rest_length = (self.particles[a].position-self.particles[b].position).len();
+        self.constraints.push(DistanceConstraint{a, b, rest_length, compliance});
+    }
+
+    #[inline]
+    pub fn particles(&self) -> &[Particle]
+    {
+        &self.particles
+    }
+
+    #[inline]
+    pub fn constraints(&self) -> &[DistanceConstraint]
+    {
+        &self.constraints
+    }
+
+    pub fn pin(&mut self, particle: usize)
+    {
+        self.particles[particle].pinned = true;
+        self.particles[particle].velocity = Vec2::zero();
+    }
+
+    pub fn unpin(&mut self, particle: usize)
+    {
+        self.particles[particle].pinned = false;
+    }
+
+    //Moves a particle, used to drag pinned ends around
+    pub fn translateParticle(&mut self, particle: usize, point: &Vec2)
+    {
+        self.particles[particle].position = *point;
+        self.particles[particle].previous = *point;
+    }
+
+    //Removes every constraint crossed by the line from start to end, returning how many were cut
+    pub fn cut(&mut self, start: &Vec2, end: &Vec2) -> usize
+    {
+        let before = self.constraints.len();
+        let particles = &self.particles;
+        self.constraints.retain(|c| !segmentsCross(start, end, &particles[c.a].position, &particles[c.b].position));
+        before-self.constraints.len()
+    }
+
+    pub fn integrate(&mut self, gravity: &Vec2, objects: &mut [Shape], dt: f64)
+    {
+        let dt = dt/SUBSTEPS as f64;
+        for _ in 0..SUBSTEPS {
+            for particle in &mut self.particles {
+                particle.previous = particle.position;
+                if !particle.pinned {
+                    particle.velocity += *gravity*dt;
+                    particle.position += particle.velocity*dt;
+                }
+            }
+
+            self.solveConstraints(dt);
+            self.collide(objects, dt);
+
+            for particle in &mut self.particles {
+                particle.velocity = (particle.position-particle.previous)/dt;
+            }
+        }
+    }
+
+    fn solveConstraints(&mut self, dt: f64)
+    {
+        for constraint in &self.constraints {
+            let (a, b) = (self.particles[constraint.a], self.particles[constraint.b]);
+            let (wA, wB) = (a.inverseMass(), b.inverseMass());
+            let alpha = constraint.compliance/(dt*dt);
+            if wA+wB+alpha == 0.0 {
+                continue;
+            }
+
+            let delta = a.position-b.position;
+            let length = delta.len();
+            if length == 0.0 {
+                continue;
+            }
+            let gradient = delta/length;
+            let lambda = -(length-constraint.rest_length)/(wA+wB+alpha);
+            self.particles[constraint.a].position += gradient*(lambda*wA);
+            self.particles[constraint.b].position += gradient*(-lambda*wB);
+        }
+    }
+
+    //Pushes particles out of the rigid bodies, with the reaction going into the body that was hit
+    fn collide(&mut self, objects: &mut [Shape], dt: f64)
+    {
+        for particle in self.particles.iter_mut().filter(|p| !p.pinned) {
+            for object in objects.iter_mut() {
+                if let Some((normal, depth)) = object.penetration(&particle.position) {
+                    particle.position += normal*depth;
+                    object.impulseAt(&particle.position, &(normal*(-depth*particle.mass/dt)));
+                }
+            }
+        }
+    }
+}
+
+//Whether segment ab crosses segment cd
+fn segmentsCross(a: &Vec2, b: &Vec2, c: &Vec2, d: &Vec2) -> bool
+{
+    let cross = |o: &Vec2, p: &Vec2, q: &Vec2| {
+        let (u, v) = (*p-*o, *q-*o);
+        u.x()*v.y()-u.y()*v.x()
+    };
+    let (d1, d2) = (cross(a, b, c), cross(a, b, d));
+    let (d3, d4) = (cross(c, d, a), cross(c, d, b));
+    d1*d2 < 0.0 && d3*d4 < 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+    use crate::DT;
+    use crate::displayable::physics::{Physics, circle::Circle, rect::Rect};
+    //Each link stays within a percent of its length
+    fn assertLinksHold(body: &PbdBody)
+    {
+        for constraint in body.constraints() {
+            let length = (body.particles[constraint.a].position-body.particles[constraint.b].position).len();
+            assert!((length-constraint.rest_length).abs() < constraint.rest_length*0.01, "link {:?} is {} long", constraint.ends(), length);
+        }
+    }
+    #[test]
+    fn rope_hangs_from_its_pin()
+    {
+        let mut rope = PbdBody::rope(Vec2::zero(), Vec2::new(100.0, 0.0), 10, 1.0, 0.0);
+        let dt = DT;
+        for _ in 0..120 {
+            rope.integrate(&Vec2::new(0.0, -500.0), &mut [], dt);
+            assertLinksHold(&rope);
+        }
+        let particles = rope.particles();
+        assert!(particles[0].pinned());
+        assert_approx_eq!(particles[0].position().len(), 0.0);
+        //Swung down from horizontal, so the free end is below the pin and never further than the rope is long
+        let end = particles[10].position();
+        assert!(end.y() < -10.0, "end at {:?}", end);
+        assert!(end.len() < 100.0+1e-6);
+    }
+    #[test]
+    fn rope_wraps_around_a_circle()
+    {
+        //Pinned to the left of a fixed ball and laid across its top
+        let mut objects = vec![Shape::Circle(Circle::new(Vec2::zero(), 40.0)).fixed()];
+        let mut rope = PbdBody::rope(Vec2::new(-60.0, 45.0), Vec2::new(140.0, 45.0), 40, 1.0, 0.0);
+        let dt = DT;
+        for _ in 0..180 {
+            rope.integrate(&Vec2::new(0.0, -500.0), &mut objects, dt);
+        }
+        let distances: Vec<f64> = rope.particles().iter().map(|p| p.position().len()).collect();
+        assert!(distances.iter().all(|&d| d > 40.0-1.0), "a particle sank into the ball: {:?}", distances);
+        //Several particles lie along the ball, and the end has slid down its far side and round below it
+        assert!(distances.iter().filter(|&&d| d < 42.0).count() >= 5, "{:?}", distances);
+        let end = rope.particles()[40].position();
+        assert!(end.y() < 0.0, "end at {:?}", end);
+        assert_approx_eq!(objects[0].position().len(), 0.0);
+    }
+    #[test]
+    fn rope_pushes_a_dynamic_rect()
+    {
+        //Falls across the right hand end of a free plank, which is pushed down and turned clockwise
+        let plank = Shape::Rect(Rect::from_centre_with_mass(Vec2::new(0.0, -100.0), Vec2::new(200.0, 20.0), 0.0, 10.0));
+        let mut objects = vec![plank];
+        let mut rope = PbdBody::rope(Vec2::new(40.0, -70.0), Vec2::new(100.0, -70.0), 10, 5.0, 0.0);
+        let dt = DT;
+        for _ in 0..30 {
+            rope.integrate(&Vec2::new(0.0, -500.0), &mut objects, dt);
+            objects[0].integrate(&Vec2::zero(), dt);
+        }
+        assert!(objects[0].velocity().y() < 0.0, "plank moving at {:?}", objects[0].velocity());
+        assert!(objects[0].angular_velocity() < 0.0, "plank turning at {}", objects[0].angular_velocity());
+    }
+    #[test]
+    fn cutting_separates_the_chain()
+    {
+        let mut rope = PbdBody::rope(Vec2::zero(), Vec2::new(100.0, 0.0), 10, 1.0, 0.0);
+        //Across the middle link, between x = 50 and x = 60
+        assert_eq!(rope.cut(&Vec2::new(55.0, 10.0), &Vec2::new(55.0, -10.0)), 1);
+        assert_eq!(rope.constraints().len(), 9);
+        assert!(rope.constraints().iter().all(|c| c.ends() != (5, 6)));
+        //Missing everything cuts nothing
+        assert_eq!(rope.cut(&Vec2::new(55.0, 10.0), &Vec2::new(55.0, 5.0)), 0);
+
+        let dt = DT;
+        for _ in 0..60 {
+            rope.integrate(&Vec2::new(0.0, -500.0), &mut [], dt);
+        }
+        //The loose half falls freely, well past where the link would have held it
+        let particles = rope.particles();
+        assert!((particles[6].position()-particles[5].position()).len() > 100.0);
+        assert!(particles[10].position().y() < -200.0);
+        assertLinksHold(&rope);
+    }
+}
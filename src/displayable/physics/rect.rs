@@ -1,6 +1,5 @@
 use crate::vec2::Vec2;
-use crate::DELTA_TIME;
-use super::{Physics, Intersect, Shape, circle::Circle};
+use super::{Physics, Intersect, Shape, circle::Circle, body::Body, sleep::SLEEPING_COLOR, sensor::SENSOR_COLOR, surface::Surface};
 use super::super::Displayable;
 
 use sdl2::pixels::Color;
@@ -17,6 +16,8 @@ pub struct Rect
     velocity: Vec2,
     angular_velocity: f64,
     mass: f64,
+    body: Body,
+    surface: Surface,
 }
 
 impl Displayable for Rect
@@ -35,10 +36,29 @@ impl Displayable for Rect
             
         points
     }
+
+    fn color(&self) -> Color
+    {
+        match self.body {
+            Body{sensor: true, ..} => SENSOR_COLOR,
+            Body{asleep: true, ..} => SLEEPING_COLOR,
+            _ => self.surface.color().unwrap_or(Color::RGB(255, 255, 255)),
+        }
+    }
 }
 
 impl Physics for Rect
 {
+    #[inline]
+    fn body(&self) -> &Body
+    {
+        &self.body
+    }
+    #[inline]
+    fn mutBody(&mut self) -> &mut Body
+    {
+        &mut self.body
+    }
     #[inline]
     fn position(&self) -> Vec2
     {
@@ -70,23 +90,29 @@ impl Physics for Rect
     #[inline]
     fn impulse(&mut self, impulse: &Vec2)
     {
-        self.velocity += *impulse
+        if !self.body.fixed {
+            self.body.wake();
+            self.velocity += *impulse
+        }
     }
     #[inline]
     fn angular_impulse(&mut self, impulse: f64)
     {
-        self.angular_velocity += impulse;
+        if !self.body.fixed {
+            self.body.wake();
+            self.angular_velocity += impulse;
+        }
     }
-    fn integrate(&mut self, gravity: &Vec2)
+    fn integrate(&mut self, gravity: &Vec2, dt: f64)
     {
-        let ang = self.angular_velocity;
-        let vel = self.velocity;
+        if self.body.fixed || self.body.asleep {
+            return;
+        }
         for i in 0..self.points.len() {
-            self.points[i]+=self.velocity*DELTA_TIME.as_millis() as f64/1000.0;
+            self.points[i]+=self.velocity*dt;
         }
-        *self = Rect::from_centre(self.position(), self.size(), self.rotation()+self.angular_velocity()*DELTA_TIME.as_millis() as f64/1000.0);
-        self.impulse(&vel);
-        self.angular_impulse(ang);
+        self.reshape(self.position(), self.size(), self.rotation()+self.angular_velocity()*dt);
+        self.velocity += *gravity*dt;
     }
     fn pointIn(&self, point: &Vec2) -> bool
     {
@@ -129,15 +155,16 @@ impl Physics for Rect
 
         //println!("{:?} {:?} {:?}", newSize, archive.size(), rotNewPoint-rotPoint);
 
-        *self = Rect::from_centre(self.position(), newSize, self.rotation());
+        self.reshape(self.position(), newSize, self.rotation());
+        self.mass = newSize.x()*newSize.y();
     }
     fn rotate(&mut self, point: &Vec2, newPoint: &Vec2, archive: &Self) {
         let (_, angle) = point.polar();
         let (_, newAngle) = newPoint.polar();
         let newRotation = archive.rotation() + (newAngle-angle);
-        *self = Rect::from_centre(self.position(), self.size(), newRotation);
+        self.reshape(self.position(), self.size(), newRotation);
     }
-    fn bounce(&mut self, other: &Shape) {
+    fn bounce(&mut self, other: &Shape, _restitution: f64) {
         match other {
             &Shape::Circle(circle) => {
             },
@@ -203,7 +230,7 @@ impl Intersect for Rect
                     (XDirection::Right, YDirection::Middle) => Vec2::new(points[3].x(), rotCirc.position().y()),
                     (XDirection::Right, YDirection::Below) => points[3],
                 };
-                if (closestPoint-rotCirc.position()).len_squared() <= rotCirc.radius().powf(2.0_f64) {
+                if (closestPoint-rotCirc.position()).len_squared() <= rotCirc.radius().powi(2) {
                     true
                 }
                 else {
@@ -258,7 +285,7 @@ impl Rect
         let pointD = (centre - Vec2::new(-size.x(), size.y())/2.0).rotate(&centre, rotation);
         
         let points = [pointA, pointB, pointC, pointD];
-        Rect{points, rotation, velocity: Vec2::zero(), angular_velocity: 0.0, mass: size.x()*size.y()}
+        Rect{points, rotation, velocity: Vec2::zero(), angular_velocity: 0.0, mass: size.x()*size.y(), body: Body::new(), surface: Surface::Normal}
     }
        
     pub fn from_centre_with_mass(centre: Vec2, size: Vec2, rotation: f64, mass: f64) -> Rect
@@ -269,7 +296,39 @@ impl Rect
         let pointD = (centre - Vec2::new(-size.x(), size.y())/2.0).rotate(&centre, rotation);
         
         let points = [pointA, pointB, pointC, pointD];
-        Rect{points, rotation, velocity: Vec2::zero(), angular_velocity: 0.0, mass}
+        Rect{points, rotation, velocity: Vec2::zero(), angular_velocity: 0.0, mass, body: Body::new(), surface: Surface::Normal}
+    }
+
+    pub fn with_surface(mut self, surface: Surface) -> Rect
+    {
+        self.surface = surface;
+        self
+    }
+
+    #[inline]
+    pub fn surface(&self) -> Surface
+    {
+        self.surface
+    }
+
+    pub fn setSurface(&mut self, surface: Surface)
+    {
+        self.surface = surface;
+    }
+
+    //Unlike impulse these leave the body's sleep state alone, for forces like drag that only ever
+    //slow a body down and so should not keep it awake
+    pub fn setVelocity(&mut self, velocity: Vec2, angular_velocity: f64)
+    {
+        self.velocity = velocity;
+        self.angular_velocity = angular_velocity;
+    }
+
+    //Moves the corners, keeping the velocities, mass and body state
+    fn reshape(&mut self, centre: Vec2, size: Vec2, rotation: f64)
+    {
+        self.points = Rect::from_centre(centre, size, rotation).points;
+        self.rotation = rotation;
     }
 
     #[inline]
@@ -295,6 +354,37 @@ impl Rect
     {
         self.rotation
     }
+
+    //The point on or inside the rectangle nearest to the given point
+    pub fn closestPoint(&self, point: &Vec2) -> Vec2
+    {
+        let centre = self.position();
+        let local = point.rotate(&centre, -self.rotation)-centre;
+        let half = self.size()/2.0;
+        let clamped = Vec2::new(local.x().clamp(-half.x().abs(), half.x().abs()), local.y().clamp(-half.y().abs(), half.y().abs()));
+        (centre+clamped).rotate(&centre, self.rotation)
+    }
+
+    //If the point is inside the rectangle, returns the outward normal of the nearest edge and how
+    //far the point must move along it to reach that edge
+    pub fn penetration(&self, point: &Vec2) -> Option<(Vec2, f64)>
+    {
+        let centre = self.position();
+        let local = point.rotate(&centre, -self.rotation)-centre;
+        let half = self.size()/2.0;
+        let dx = half.x().abs()-local.x().abs();
+        let dy = half.y().abs()-local.y().abs();
+        if dx <= 0.0 || dy <= 0.0 {
+            return None;
+        }
+        let normal = if dx < dy {
+            Vec2::new(local.x().signum(), 0.0)
+        }
+        else {
+            Vec2::new(0.0, local.y().signum())
+        };
+        Some((normal.rotate(&Vec2::zero(), self.rotation), dx.min(dy)))
+    }
 }
 
 
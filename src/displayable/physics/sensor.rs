@@ -0,0 +1,115 @@
+use super::{Physics, Intersect, Shape};
+
+use sdl2::pixels::Color;
+
+use std::collections::BTreeSet;
+
+pub const SENSOR_COLOR: Color = Color::RGB(80, 220, 120);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SensorEvent
+{
+    //Body ids, from Body::id
+    Begin{sensor: u64, other: u64},
+    End{sensor: u64, other: u64},
+}
+
+//Tracks what overlaps each sensor from one step to the next
+#[derive(Debug, Clone)]
+pub struct Sensors
+{
+    overlaps: BTreeSet<(u64, u64)>,
+    events: Vec<SensorEvent>,
+}
+
+impl Sensors
+{
+    pub fn new() -> Sensors
+    {
+        Sensors{overlaps: BTreeSet::new(), events: Vec::new()}
+    }
+
+    //Call once per step, after the bodies have moved
+    pub fn update(&mut self, objects: &[Shape])
+    {
+        let mut overlaps = BTreeSet::new();
+        for sensor in objects.iter().filter(|o| o.body().sensor) {
+            for other in objects {
+                if other.body().id == sensor.body().id || !sensor.body().filter.shouldCollide(&other.body().filter) {
+                    continue;
+                }
+                if sensor.intersect(other) {
+                    overlaps.insert((sensor.body().id, other.body().id));
+                }
+            }
+        }
+
+        self.events.clear();
+        for (sensor, other) in overlaps.difference(&self.overlaps) {
+            self.events.push(SensorEvent::Begin{sensor: *sensor, other: *other});
+        }
+        for (sensor, other) in self.overlaps.difference(&overlaps) {
+            self.events.push(SensorEvent::End{sensor: *sensor, other: *other});
+        }
+        self.overlaps = overlaps;
+    }
+
+    //What started or stopped overlapping in the last update
+    #[inline]
+    pub fn events(&self) -> &[SensorEvent]
+    {
+        &self.events
+    }
+
+    //Ids of every body currently inside the sensor
+    pub fn overlapping(&self, sensor: u64) -> impl Iterator<Item = u64> + '_
+    {
+        self.overlaps.range((sensor, 0)..=(sensor, u64::MAX)).map(|(_, other)| *other)
+    }
+
+    pub fn isOverlapping(&self, sensor: u64, other: u64) -> bool
+    {
+        self.overlaps.contains(&(sensor, other))
+    }
+}
+
+impl Default for Sensors
+{
+    fn default() -> Sensors
+    {
+        Sensors::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec2::Vec2;
+    use crate::displayable::physics::{circle::Circle, rect::Rect, detectIntersections};
+    #[test]
+    fn begin_and_end()
+    {
+        let mut objects = vec![
+            Shape::Rect(Rect::from_centre(Vec2::zero(), Vec2::new(100.0, 100.0), 0.0)).sensor(),
+            Shape::Circle(Circle::new(Vec2::new(-200.0, 0.0), 10.0)),
+        ];
+        let (sensor, ball) = (objects[0].body().id, objects[1].body().id);
+        let mut sensors = Sensors::new();
+
+        sensors.update(&objects);
+        assert!(sensors.events().is_empty());
+
+        objects[1].translateTo(&Vec2::zero());
+        assert!(detectIntersections(&objects).is_empty());
+        sensors.update(&objects);
+        assert_eq!(sensors.events(), &[SensorEvent::Begin{sensor, other: ball}]);
+        assert_eq!(sensors.overlapping(sensor).collect::<Vec<_>>(), vec![ball]);
+
+        sensors.update(&objects);
+        assert!(sensors.events().is_empty());
+
+        objects[1].translateTo(&Vec2::new(200.0, 0.0));
+        sensors.update(&objects);
+        assert_eq!(sensors.events(), &[SensorEvent::End{sensor, other: ball}]);
+    }
+}
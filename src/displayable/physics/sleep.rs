@@ -0,0 +1,174 @@
+use super::{Physics, Shape};
+
+use sdl2::pixels::Color;
+
+//Bodies slower than this, for longer than TIME_TO_SLEEP seconds, may fall asleep
+const LINEAR_SLEEP_SPEED: f64 = 5.0;
+const ANGULAR_SLEEP_SPEED: f64 = 0.05;
+const TIME_TO_SLEEP: f64 = 0.5;
+
+pub const SLEEPING_COLOR: Color = Color::RGB(110, 110, 130);
+
+fn find(parents: &mut [usize], i: usize) -> usize
+{
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    //Point everything on the way straight at the root so later lookups are quick
+    let mut i = i;
+    while parents[i] != root {
+        let next = parents[i];
+        parents[i] = root;
+        i = next;
+    }
+    root
+}
+
+//Groups the dynamic bodies that are touching, directly or through each other. Fixed bodies do not
+//join islands together, or a whole scene resting on the floor would be one island
+pub fn islands(objects: &[Shape], contacts: &[(usize, usize)]) -> Vec<Vec<usize>>
+{
+    let mut parents: Vec<usize> = (0..objects.len()).collect();
+    for (a, b) in contacts {
+        if objects[*a].body().fixed || objects[*b].body().fixed {
+            continue;
+        }
+        let (rootA, rootB) = (find(&mut parents, *a), find(&mut parents, *b));
+        if rootA != rootB {
+            parents[rootA.max(rootB)] = rootA.min(rootB);
+        }
+    }
+
+    let mut islands: Vec<Vec<usize>> = Vec::new();
+    let mut islandOf = vec![usize::MAX; objects.len()];
+    for (i, object) in objects.iter().enumerate() {
+        if object.body().fixed {
+            continue;
+        }
+        let root = find(&mut parents, i);
+        if islandOf[root] == usize::MAX {
+            islandOf[root] = islands.len();
+            islands.push(Vec::new());
+        }
+        islands[islandOf[root]].push(i);
+    }
+    islands
+}
+
+//Islands sleep together once every body in them has been resting long enough, and wake together as
+//soon as any of them is moving. A sleeping body touching an awake one is in its island, so it wakes
+pub fn updateSleep(objects: &mut [Shape], contacts: &[(usize, usize)], dt: f64)
+{
+    for object in objects.iter_mut() {
+        if object.body().fixed || object.body().asleep {
+            continue;
+        }
+        let resting = object.velocity().len() < LINEAR_SLEEP_SPEED && object.angular_velocity().abs() < ANGULAR_SLEEP_SPEED;
+        let body = object.mutBody();
+        body.rest_time = if resting { body.rest_time+dt } else { 0.0 };
+    }
+
+    for island in islands(objects, contacts) {
+        let tired = island.iter().all(|i| objects[*i].body().asleep || objects[*i].body().rest_time >= TIME_TO_SLEEP);
+        let sleeping = island.iter().all(|i| objects[*i].body().asleep);
+        if tired && !sleeping {
+            for i in island {
+                let object = &mut objects[i];
+                object.impulse(&(object.velocity()*-1.0));
+                object.angular_impulse(-object.angular_velocity());
+                object.mutBody().asleep = true;
+            }
+        }
+        else if !tired {
+            for i in island {
+                if objects[i].body().asleep {
+                    objects[i].mutBody().wake();
+                }
+            }
+        }
+    }
+}
+
+pub fn wakeAll(objects: &mut [Shape])
+{
+    for object in objects.iter_mut() {
+        object.mutBody().wake();
+    }
+}
+
+//Wakes a body and everything in its island, leaving the rest of the scene asleep
+pub fn wakeIsland(objects: &mut [Shape], contacts: &[(usize, usize)], i: usize)
+{
+    let island = islands(objects, contacts).into_iter().find(|island| island.contains(&i)).unwrap_or(vec![i]);
+    for i in island {
+        objects[i].mutBody().wake();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DT;
+    use crate::vec2::Vec2;
+    use crate::displayable::physics::{circle::Circle, rect::Rect};
+    #[test]
+    fn islands_sleep_and_wake_together()
+    {
+        let mut objects = vec![
+            Shape::Rect(Rect::from_centre(Vec2::new(0.0, -10.0), Vec2::new(1000.0, 20.0), 0.0)).fixed(),
+            Shape::Circle(Circle::new(Vec2::new(-15.0, 10.0), 10.0)),
+            Shape::Circle(Circle::new(Vec2::new(5.0, 10.0), 10.0)),
+            Shape::Circle(Circle::new(Vec2::new(300.0, 10.0), 10.0)),
+        ];
+        let contacts = [(0, 1), (0, 2), (0, 3), (1, 2)];
+        assert_eq!(islands(&objects, &contacts), vec![vec![1, 2], vec![3]]);
+
+        objects[3].impulse(&Vec2::new(100.0, 0.0));
+        for _ in 0..60 {
+            updateSleep(&mut objects, &contacts, DT);
+        }
+        assert!(objects[1].body().asleep && objects[2].body().asleep);
+        assert!(!objects[3].body().asleep);
+
+        //The moving circle reaches the pile and wakes all of it
+        updateSleep(&mut objects, &[(1, 2), (2, 3)], DT);
+        assert!(!objects[1].body().asleep && !objects[2].body().asleep);
+    }
+    #[test]
+    fn waking_one_island()
+    {
+        let mut objects = vec![
+            Shape::Rect(Rect::from_centre(Vec2::new(0.0, -10.0), Vec2::new(1000.0, 20.0), 0.0)).fixed(),
+            Shape::Circle(Circle::new(Vec2::new(-15.0, 10.0), 10.0)),
+            Shape::Circle(Circle::new(Vec2::new(5.0, 10.0), 10.0)),
+            Shape::Circle(Circle::new(Vec2::new(300.0, 10.0), 10.0)),
+        ];
+        for object in &mut objects[1..] {
+            object.mutBody().asleep = true;
+        }
+        let contacts = [(0, 1), (0, 2), (0, 3), (1, 2)];
+        wakeIsland(&mut objects, &contacts, 2);
+        assert!(!objects[1].body().asleep && !objects[2].body().asleep);
+        assert!(objects[3].body().asleep);
+    }
+    #[test]
+    fn resting_on_the_floor_in_a_world()
+    {
+        //Dropped onto a fixed floor under gravity, for dead, lively and perfectly elastic contacts
+        for restitution in [0.0, 0.5, 1.0] {
+            let mut world = crate::world::World::new();
+            world.gravity = Vec2::new(0.0, -500.0);
+            world.settings.restitution = restitution;
+            world.objects.push(Shape::Rect(Rect::from_centre(Vec2::new(0.0, -10.0), Vec2::new(1000.0, 20.0), 0.0)).fixed());
+            world.objects.push(Shape::Circle(Circle::new(Vec2::new(0.0, 40.0), 10.0)));
+            let mut steps = 0;
+            while !world.objects[1].body().asleep {
+                world.step(DT);
+                steps += 1;
+                assert!(steps < 600, "restitution {} never slept", restitution);
+            }
+            assert!(world.objects[1].position().y() > 9.0);
+        }
+    }
+}
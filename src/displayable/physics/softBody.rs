@@ -0,0 +1,422 @@
+use crate::vec2::Vec2;
+use super::{Physics, Shape};
+use super::super::Displayable;
+
+use serde::{Serialize, Deserialize};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+
+use bresenham::Bresenham;
+
+//Springs are stiff compared to the frame time, so each frame is split into several smaller steps
+const SUBSTEPS: u32 = 8;
+const RESTITUTION: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PointMass
+{
+    position: Vec2,
+    velocity: Vec2,
+    //Only lives for one substep
+    #[serde(skip, default = "Vec2::zero")]
+    force: Vec2,
+    mass: f64,
+}
+
+impl PointMass
+{
+    pub fn new(position: Vec2, mass: f64) -> PointMass
+    {
+        PointMass{position, velocity: Vec2::zero(), force: Vec2::zero(), mass}
+    }
+
+    #[inline]
+    pub fn position(&self) -> Vec2
+    {
+        self.position
+    }
+
+    #[inline]
+    pub fn velocity(&self) -> Vec2
+    {
+        self.velocity
+    }
+
+    #[inline]
+    pub fn mass(&self) -> f64
+    {
+        self.mass
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SpringKind
+{
+    //Joins neighbouring points along the edges of the lattice
+    Structural,
+    //Joins points diagonally, stops the lattice from shearing flat
+    Shear,
+    //Joins points two apart, resists folding
+    Bending,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Spring
+{
+    a: usize,
+    b: usize,
+    rest_length: f64,
+    stiffness: f64,
+    damping: f64,
+    kind: SpringKind,
+}
+
+impl Spring
+{
+    pub fn kind(&self) -> SpringKind
+    {
+        self.kind
+    }
+
+    pub fn ends(&self) -> (usize, usize)
+    {
+        (self.a, self.b)
+    }
+
+    pub fn restLength(&self) -> f64
+    {
+        self.rest_length
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoftBody
+{
+    points: Vec<PointMass>,
+    springs: Vec<Spring>,
+    //Indices of the points on the outside of the body, anticlockwise
+    hull: Vec<usize>,
+    //Gas pressure constant, None if the body does not try to keep its area
+    pressure: Option<f64>,
+    rest_area: f64,
+}
+
+impl Displayable for SoftBody
+{
+    fn display(&self) -> Vec<(Point, Color)>
+    {
+        let extr = |a: Vec2| {let a = Point::from(a); (a.x() as isize, a.y() as isize)};
+
+        let mut points = Vec::new();
+        for (i, index) in self.hull.iter().enumerate() {
+            let next = self.hull[(i+1)%self.hull.len()];
+            let line = Bresenham::new(extr(self.points[*index].position), extr(self.points[next].position))
+                .map(|a| (Point::new(a.0 as i32, a.1 as i32), self.color()));
+            points.extend(line);
+        }
+        points
+    }
+}
+
+impl SoftBody
+{
+    //A lattice of cols*rows points filling the rectangle. cols and rows must be at least 2
+    pub fn rect(centre: Vec2, size: Vec2, cols: usize, rows: usize, mass: f64, stiffness: f64, damping: f64) -> SoftBody
+    {
+        assert!(cols >= 2 && rows >= 2, "A soft rectangle needs at least 2 points in each direction");
+
+        let pointMass = mass/(cols*rows) as f64;
+        let bottomLeft = centre-size/2.0;
+        let spacing = Vec2::new(size.x()/(cols-1) as f64, size.y()/(rows-1) as f64);
+        let index = |col: usize, row: usize| row*cols+col;
+
+        let mut points = Vec::with_capacity(cols*rows);
+        for row in 0..rows {
+            for col in 0..cols {
+                let position = bottomLeft+Vec2::new(spacing.x()*col as f64, spacing.y()*row as f64);
+                points.push(PointMass::new(position, pointMass));
+            }
+        }
+
+        let mut body = SoftBody{points, springs: Vec::new(), hull: Vec::new(), pressure: None, rest_area: 0.0};
+        for row in 0..rows {
+            for col in 0..cols {
+                if col+1 < cols {
+                    body.connect(index(col, row), index(col+1, row), stiffness, damping, SpringKind::Structural);
+                }
+                if row+1 < rows {
+                    body.connect(index(col, row), index(col, row+1), stiffness, damping, SpringKind::Structural);
+                }
+                if col+1 < cols && row+1 < rows {
+                    body.connect(index(col, row), index(col+1, row+1), stiffness, damping, SpringKind::Shear);
+                    body.connect(index(col+1, row), index(col, row+1), stiffness, damping, SpringKind::Shear);
+                }
+                if col+2 < cols {
+                    body.connect(index(col, row), index(col+2, row), stiffness, damping, SpringKind::Bending);
+                }
+                if row+2 < rows {
+                    body.connect(index(col, row), index(col, row+2), stiffness, damping, SpringKind::Bending);
+                }
+            }
+        }
+
+        //Walk around the outside anticlockwise, starting in the bottom left corner
+        let mut hull = Vec::with_capacity(2*(cols+rows));
+        hull.extend((0..cols).map(|col| index(col, 0)));
+        hull.extend((1..rows).map(|row| index(cols-1, row)));
+        hull.extend((0..cols-1).rev().map(|col| index(col, rows-1)));
+        hull.extend((1..rows-1).rev().map(|row| index(0, row)));
+        body.hull = hull;
+        body.rest_area = body.area();
+        body
+    }
+
+    //A ring of segments points around a centre point, joined to it by spokes
+    pub fn circle(centre: Vec2, radius: f64, segments: usize, mass: f64, stiffness: f64, damping: f64) -> SoftBody
+    {
+        assert!(segments >= 3, "A soft circle needs at least 3 segments");
+
+        let pointMass = mass/(segments+1) as f64;
+        let mut points = Vec::with_capacity(segments+1);
+        for i in 0..segments {
+            let angle = std::f64::consts::TAU*i as f64/segments as f64;
+            points.push(PointMass::new(centre+Vec2::from_polar(radius, angle), pointMass));
+        }
+        points.push(PointMass::new(centre, pointMass));
+
+        let mut body = SoftBody{points, springs: Vec::new(), hull: (0..segments).collect(), pressure: None, rest_area: 0.0};
+        for i in 0..segments {
+            body.connect(i, (i+1)%segments, stiffness, damping, SpringKind::Structural);
+            body.connect(i, segments, stiffness, damping, SpringKind::Structural);
+            body.connect(i, (i+2)%segments, stiffness, damping, SpringKind::Bending);
+        }
+        body.rest_area = body.area();
+        body
+    }
+
+    //Makes the body push outwards on its hull to keep its area, like a balloon
+    pub fn with_pressure(mut self, pressure: f64) -> SoftBody
+    {
+        self.pressure = Some(pressure);
+        self
+    }
+
+    fn connect(&mut self, a: usize, b: usize, stiffness: f64, damping: f64, kind: SpringKind)
+    {
+        let rest_length = (self.points[a].position-self.points[b].position).len();
+        self.springs.push(Spring{a, b, rest_length, stiffness, damping, kind});
+    }
+
+    #[inline]
+    pub fn points(&self) -> &[PointMass]
+    {
+        &self.points
+    }
+
+    #[inline]
+    pub fn springs(&self) -> &[Spring]
+    {
+        &self.springs
+    }
+
+    #[inline]
+    pub fn hull(&self) -> &[usize]
+    {
+        &self.hull
+    }
+
+    pub fn mass(&self) -> f64
+    {
+        self.points.iter().map(|p| p.mass).sum()
+    }
+
+    pub fn position(&self) -> Vec2
+    {
+        self.points.iter().fold(Vec2::zero(), |acc, p| acc+p.position*p.mass)/self.mass()
+    }
+
+    pub fn velocity(&self) -> Vec2
+    {
+        self.points.iter().fold(Vec2::zero(), |acc, p| acc+p.velocity*p.mass)/self.mass()
+    }
+
+    //Signed area enclosed by the hull, positive while the hull is anticlockwise
+    pub fn area(&self) -> f64
+    {
+        let mut area = 0.0;
+        for (i, index) in self.hull.iter().enumerate() {
+            let a = self.points[*index].position;
+            let b = self.points[self.hull[(i+1)%self.hull.len()]].position;
+            area += a.x()*b.y()-b.x()*a.y();
+        }
+        area/2.0
+    }
+
+    //Energy stored in the springs. The pressure of the gas inside is not counted
+    pub fn potential(&self) -> f64
+    {
+        self.springs.iter()
+            .map(|spring| {
+                let stretch = (self.points[spring.a].position-self.points[spring.b].position).len()-spring.rest_length;
+                0.5*spring.stiffness*stretch*stretch
+            })
+            .sum()
+    }
+
+    pub fn impulse(&mut self, impulse: &Vec2)
+    {
+        for point in &mut self.points {
+            point.velocity += *impulse;
+        }
+    }
+
+    pub fn integrate(&mut self, gravity: &Vec2, objects: &mut [Shape], dt: f64)
+    {
+        let dt = dt/SUBSTEPS as f64;
+        for _ in 0..SUBSTEPS {
+            self.accumulateForces(gravity);
+            for point in &mut self.points {
+                point.velocity += point.force*(dt/point.mass);
+                point.position += point.velocity*dt;
+            }
+            self.collide(objects);
+        }
+    }
+
+    fn accumulateForces(&mut self, gravity: &Vec2)
+    {
+        for point in &mut self.points {
+            point.force = *gravity*point.mass;
+        }
+
+        for spring in &self.springs {
+            let (a, b) = (self.points[spring.a], self.points[spring.b]);
+            let delta = b.position-a.position;
+            let length = delta.len();
+            if length == 0.0 {
+                continue;
+            }
+            let direction = delta/length;
+            let stretch = length-spring.rest_length;
+            let closing = Vec2::dot(&(b.velocity-a.velocity), &direction);
+            let force = direction*(spring.stiffness*stretch+spring.damping*closing);
+            self.points[spring.a].force += force;
+            self.points[spring.b].force += force*-1.0;
+        }
+
+        if let Some(pressure) = self.pressure {
+            //Ideal gas: pressure is inversely proportional to area, and pushes each hull edge along
+            //its outward normal in proportion to the edge's length
+            let area = self.area().max(self.rest_area*0.01);
+            let p = pressure*self.rest_area/area;
+            for (i, index) in self.hull.iter().enumerate() {
+                let next = self.hull[(i+1)%self.hull.len()];
+                let edge = self.points[next].position-self.points[*index].position;
+                let outward = Vec2::new(edge.y(), -edge.x());
+                let force = outward*(p/2.0);
+                self.points[*index].force += force;
+                self.points[next].force += force;
+            }
+        }
+    }
+
+    //Pushes points out of the rigid bodies, with the reaction going into the body that was hit
+    fn collide(&mut self, objects: &mut [Shape])
+    {
+        for point in &mut self.points {
+            for object in objects.iter_mut() {
+                if let Some((normal, depth)) = object.penetration(&point.position) {
+                    point.position += normal*depth;
+                    let approach = Vec2::dot(&(point.velocity-object.velocity()), &normal);
+                    if approach < 0.0 {
+                        let change = normal*(-approach*(1.0+RESTITUTION));
+                        point.velocity += change;
+                        object.impulseAt(&point.position, &(change*-point.mass));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+    use crate::DT;
+    use crate::displayable::physics::rect::Rect;
+    #[test]
+    fn springs_rest_at_their_length()
+    {
+        let mut body = SoftBody::rect(Vec2::zero(), Vec2::new(60.0, 40.0), 4, 3, 12.0, 2000.0, 5.0);
+        assert_approx_eq!(body.potential(), 0.0);
+        assert_approx_eq!(body.area(), 60.0*40.0);
+
+        //Pulling a corner out stores energy, which the damping then drains as it springs back
+        body.points[0].position += Vec2::new(-10.0, -10.0);
+        let stretched = body.potential();
+        assert!(stretched > 0.0);
+        let dt = DT;
+        for _ in 0..300 {
+            body.integrate(&Vec2::zero(), &mut [], dt);
+        }
+        assert!(body.potential() < stretched*1e-3, "{} of {} left", body.potential(), stretched);
+        for spring in body.springs() {
+            let (a, b) = spring.ends();
+            let length = (body.points[a].position-body.points[b].position).len();
+            assert!((length-spring.rest_length).abs() < 0.5, "spring {:?} is {} long, rests at {}", spring.kind(), length, spring.rest_length);
+        }
+    }
+    #[test]
+    fn pressure_keeps_the_area()
+    {
+        //A floppy ring squashed against the floor by gravity
+        let floor = Shape::Rect(Rect::from_centre(Vec2::new(0.0, -100.0), Vec2::new(1000.0, 20.0), 0.0)).fixed();
+        let settle = |mut body: SoftBody| {
+            let mut objects = vec![floor.clone()];
+            let dt = DT;
+            for _ in 0..300 {
+                body.integrate(&Vec2::new(0.0, -500.0), &mut objects, dt);
+            }
+            body.area()/body.rest_area
+        };
+        let slack = settle(SoftBody::circle(Vec2::zero(), 40.0, 16, 10.0, 20.0, 1.0));
+        let inflated = settle(SoftBody::circle(Vec2::zero(), 40.0, 16, 10.0, 20.0, 1.0).with_pressure(20.0));
+        assert!(slack < 0.5, "the slack ring kept {} of its area", slack);
+        assert!((inflated-1.0).abs() < 0.1, "the inflated ring kept {} of its area", inflated);
+    }
+    #[test]
+    fn lands_on_a_fixed_rect()
+    {
+        let floor = Shape::Rect(Rect::from_centre(Vec2::new(0.0, -100.0), Vec2::new(1000.0, 20.0), 0.0)).fixed();
+        let mut objects = vec![floor];
+        let mut body = SoftBody::rect(Vec2::zero(), Vec2::new(60.0, 40.0), 4, 3, 12.0, 2000.0, 5.0);
+        let dt = DT;
+        for _ in 0..300 {
+            body.integrate(&Vec2::new(0.0, -500.0), &mut objects, dt);
+        }
+        //Resting on the top face at y = -90, not fallen through and not moving the wall
+        for point in body.points() {
+            assert!(point.position().y() > -91.0, "point at {:?} is in the floor", point.position());
+        }
+        assert!(body.position().y() < -60.0);
+        assert!(body.velocity().len() < 5.0);
+        assert_approx_eq!(objects[0].position().y(), -100.0);
+    }
+    #[test]
+    fn pushes_a_dynamic_rect()
+    {
+        //Dropped onto the right hand end of a free plank, which is pushed down and turned clockwise
+        let plank = Shape::Rect(Rect::from_centre_with_mass(Vec2::new(0.0, -100.0), Vec2::new(200.0, 20.0), 0.0, 50.0));
+        let mut objects = vec![plank];
+        let mut body = SoftBody::rect(Vec2::new(60.0, -60.0), Vec2::new(40.0, 40.0), 3, 3, 12.0, 2000.0, 5.0);
+        let dt = DT;
+        for _ in 0..30 {
+            body.integrate(&Vec2::new(0.0, -500.0), &mut objects, dt);
+            objects[0].integrate(&Vec2::zero(), dt);
+        }
+        assert!(objects[0].velocity().y() < 0.0, "plank moving at {:?}", objects[0].velocity());
+        assert!(objects[0].angular_velocity() < 0.0, "plank turning at {}", objects[0].angular_velocity());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::vec2::Vec2;
+use super::{Physics, Shape, contact::{Contact, ContactListener}};
+
+use serde::{Serialize, Deserialize};
+
+use sdl2::pixels::Color;
+
+//A rect with a surface only gets it on its top edge, with up being the rect's own +y once rotated
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Surface
+{
+    Normal,
+    //Bodies pass up through it from below and land on it from above
+    OneWay,
+    //Carries touching bodies along at this speed, positive towards the rect's +x
+    Conveyor(f64),
+    //Overrides the restitution of anything bouncing off it
+    Bouncy(f64),
+}
+
+impl Surface
+{
+    pub fn color(&self) -> Option<Color>
+    {
+        match self {
+            Surface::Normal => None,
+            Surface::OneWay => Some(Color::RGB(240, 220, 60)),
+            Surface::Conveyor(_) => Some(Color::RGB(255, 140, 40)),
+            Surface::Bouncy(_) => Some(Color::RGB(240, 80, 200)),
+        }
+    }
+
+    //The order the editor steps through the surfaces in
+    pub fn next(&self) -> Surface
+    {
+        match self {
+            Surface::Normal => Surface::OneWay,
+            Surface::OneWay => Surface::Conveyor(200.0),
+            Surface::Conveyor(speed) if *speed > 0.0 => Surface::Conveyor(-speed),
+            Surface::Conveyor(_) => Surface::Bouncy(1.5),
+            Surface::Bouncy(_) => Surface::Normal,
+        }
+    }
+}
+
+//Applies the surfaces of any rects in a contact. The contact manager registers one of these itself
+pub struct SurfaceListener;
+
+impl ContactListener for SurfaceListener
+{
+    fn preSolve(&mut self, contact: &mut Contact, a: &Shape, b: &Shape)
+    {
+        //Normal pointing out of the surface, towards the other body
+        let (rect, other, normal) = match (a, b) {
+            (Shape::Rect(rect), _) => (rect, b, contact.normal),
+            (_, Shape::Rect(rect)) => (rect, a, contact.normal*-1.0),
+            _ => return,
+        };
+        let up = Vec2::new(0.0, 1.0).rotate(&Vec2::zero(), rect.rotation());
+
+        match rect.surface() {
+            Surface::Normal => (),
+            Surface::OneWay => {
+                //Anything rising, or not yet clear of the top edge, goes through
+                let rising = Vec2::dot(&(other.velocity()-rect.velocity()), &up) > 0.0;
+                if rising || Vec2::dot(&normal, &up) < 0.5 {
+                    contact.enabled = false;
+                }
+            },
+            Surface::Conveyor(speed) => {
+                if Vec2::dot(&normal, &up) > 0.5 {
+                    contact.surface_velocity = rect.velocity()+Vec2::new(speed, 0.0).rotate(&Vec2::zero(), rect.rotation());
+                }
+            },
+            Surface::Bouncy(restitution) => contact.restitution = restitution,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::World;
+    use crate::displayable::physics::{circle::Circle, rect::Rect};
+    #[test]
+    fn one_way_platform()
+    {
+        //A platform whose top is at y = 10, a ball thrown up through it from below and one dropped on it
+        let mut world = World::new();
+        world.gravity = Vec2::new(0.0, -500.0);
+        world.objects.push(Shape::Rect(Rect::from_centre(Vec2::zero(), Vec2::new(300.0, 20.0), 0.0).with_surface(Surface::OneWay)).fixed());
+        let mut rising = Circle::new(Vec2::new(-50.0, -40.0), 10.0);
+        rising.impulse(&Vec2::new(0.0, 400.0));
+        world.objects.push(Shape::Circle(rising));
+        world.objects.push(Shape::Circle(Circle::new(Vec2::new(50.0, 100.0), 10.0)));
+
+        let dt = crate::DT;
+        let mut highest: f64 = -40.0;
+        for _ in 0..180 {
+            world.step(dt);
+            highest = highest.max(world.objects[1].position().y());
+        }
+        //The rising ball went up through it, came back down and landed on top, as did the other
+        assert!(highest > 100.0);
+        for ball in &world.objects[1..] {
+            assert!(ball.position().y()-10.0 > 10.0-1.0, "ball at {:?}", ball.position());
+        }
+        assert_eq!(world.objects[0].position(), Vec2::zero());
+    }
+    #[test]
+    fn conveyor_carries_a_resting_ball()
+    {
+        //A belt tilted slightly, so its speed is along the belt rather than straight along x
+        for speed in [200.0, -150.0] {
+            let mut world = World::new();
+            world.gravity = Vec2::new(0.0, -500.0);
+            world.settings.restitution = 0.0;
+            world.objects.push(Shape::Rect(Rect::from_centre(Vec2::zero(), Vec2::new(2000.0, 20.0), 0.1).with_surface(Surface::Conveyor(speed))).fixed());
+            world.objects.push(Shape::Circle(Circle::new(Vec2::new(0.0, 30.0), 10.0)));
+            for _ in 0..60 {
+                world.step(crate::DT);
+            }
+            let along = Vec2::new(1.0, 0.0).rotate(&Vec2::zero(), 0.1);
+            let velocity = world.objects[1].velocity();
+            assert!((Vec2::dot(&velocity, &along)-speed).abs() < 1.0, "belt at {} left the ball at {:?}", speed, velocity);
+        }
+    }
+    #[test]
+    fn bouncy_overrides_restitution()
+    {
+        //The world would bounce perfectly, the surface takes half of the speed it is hit at
+        let mut world = World::new();
+        world.gravity = Vec2::new(0.0, -500.0);
+        world.settings.restitution = 1.0;
+        world.objects.push(Shape::Rect(Rect::from_centre(Vec2::zero(), Vec2::new(300.0, 20.0), 0.0).with_surface(Surface::Bouncy(0.5))).fixed());
+        world.objects.push(Shape::Circle(Circle::new(Vec2::new(0.0, 100.0), 10.0)));
+        let mut impact = 0.0;
+        for _ in 0..120 {
+            let before = world.objects[1].velocity().y();
+            world.step(crate::DT);
+            if before < 0.0 && world.objects[1].velocity().y() > 0.0 {
+                impact = before;
+                break;
+            }
+        }
+        assert!(impact < -200.0);
+        assert!((world.objects[1].velocity().y()+impact*0.5).abs() < 1e-9, "hit at {}, left at {:?}", impact, world.objects[1].velocity());
+    }
+}
@@ -0,0 +1,287 @@
+use crate::world::World;
+use crate::diagnostics::Diagnostics;
+use crate::displayable::physics::Physics;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write, BufWriter};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format
+{
+    Csv,
+    JsonLines,
+}
+
+impl Format
+{
+    //From the file extension: .csv, or .jsonl/.ndjson
+    pub fn from_path(path: &Path) -> Option<Format>
+    {
+        match path.extension()?.to_str()? {
+            "csv" => Some(Format::Csv),
+            "jsonl" | "ndjson" => Some(Format::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+//What can be written for each body. Vectors take two CSV columns, _x and _y, and are arrays in JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity
+{
+    Position,
+    Velocity,
+    AngularVelocity,
+    KineticEnergy,
+    //How many other bodies it touched in the last step
+    Contacts,
+}
+
+impl Quantity
+{
+    pub const ALL: [Quantity; 5] = [Quantity::Position, Quantity::Velocity, Quantity::AngularVelocity, Quantity::KineticEnergy, Quantity::Contacts];
+
+    pub fn name(&self) -> &'static str
+    {
+        match self {
+            Quantity::Position => "position",
+            Quantity::Velocity => "velocity",
+            Quantity::AngularVelocity => "angular_velocity",
+            Quantity::KineticEnergy => "kinetic_energy",
+            Quantity::Contacts => "contacts",
+        }
+    }
+}
+
+impl FromStr for Quantity
+{
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Quantity, String>
+    {
+        Quantity::ALL.iter().find(|q| q.name() == name).copied().ok_or(format!("unknown quantity {}", name))
+    }
+}
+
+impl fmt::Display for Quantity
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}", self.name())
+    }
+}
+
+//Writes chosen quantities for every body, one row per body, every so many steps, or the world's
+//momentum and energy, one row per step. Call sample after each step; the step and time written
+//count from when the export started
+pub struct Exporter<W: Write>
+{
+    out: W,
+    format: Format,
+    quantities: Vec<Quantity>,
+    diagnostics: bool,
+    interval: usize,
+    steps: usize,
+    time: f64,
+    started: bool,
+}
+
+impl<W: Write> Exporter<W>
+{
+    //Everything, every step
+    pub fn new(out: W, format: Format) -> Exporter<W>
+    {
+        Exporter{out, format, quantities: Quantity::ALL.to_vec(), diagnostics: false, interval: 1, steps: 0, time: 0.0, started: false}
+    }
+
+    pub fn with_quantities(mut self, quantities: Vec<Quantity>) -> Self
+    {
+        self.quantities = quantities;
+        self
+    }
+
+    //Totals for the whole world instead of rows for each body
+    pub fn with_diagnostics(mut self) -> Self
+    {
+        self.diagnostics = true;
+        self
+    }
+
+    //Write every nth step. Anything below 1 is taken as 1
+    pub fn with_interval(mut self, interval: usize) -> Self
+    {
+        self.interval = interval.max(1);
+        self
+    }
+
+    pub fn format(&self) -> Format
+    {
+        self.format
+    }
+
+    pub fn sample(&mut self, world: &World, dt: f64) -> io::Result<()>
+    {
+        self.steps += 1;
+        self.time += dt;
+        if !self.steps.is_multiple_of(self.interval) {
+            return Ok(());
+        }
+        if self.diagnostics {
+            return self.writeDiagnostics(&Diagnostics::measure(world));
+        }
+        if !self.started && self.format == Format::Csv {
+            let mut header = vec!["step".to_string(), "time".to_string(), "id".to_string()];
+            for quantity in &self.quantities {
+                match quantity {
+                    Quantity::Position | Quantity::Velocity => {
+                        header.push(format!("{}_x", quantity));
+                        header.push(format!("{}_y", quantity));
+                    },
+                    _ => header.push(quantity.to_string()),
+                }
+            }
+            writeln!(self.out, "{}", header.join(","))?;
+        }
+        self.started = true;
+
+        let mut contacts: BTreeMap<u64, usize> = BTreeMap::new();
+        for (a, b) in world.contacts().touching() {
+            *contacts.entry(*a).or_insert(0) += 1;
+            *contacts.entry(*b).or_insert(0) += 1;
+        }
+
+        for object in &world.objects {
+            let id = object.body().id;
+            let mut fields: Vec<(&str, String)> = Vec::new();
+            for quantity in &self.quantities {
+                let value = match quantity {
+                    Quantity::Position => vector(self.format, object.position().x(), object.position().y()),
+                    Quantity::Velocity => vector(self.format, object.velocity().x(), object.velocity().y()),
+                    Quantity::AngularVelocity => number(self.format, object.angular_velocity()),
+                    Quantity::KineticEnergy => number(self.format, object.kineticEnergy()),
+                    Quantity::Contacts => contacts.get(&id).copied().unwrap_or(0).to_string(),
+                };
+                fields.push((quantity.name(), value));
+            }
+            match self.format {
+                Format::Csv => {
+                    let values: Vec<String> = fields.into_iter().map(|(_, value)| value).collect();
+                    writeln!(self.out, "{},{},{},{}", self.steps, self.time, id, values.join(","))?;
+                },
+                Format::JsonLines => {
+                    let values: Vec<String> = fields.into_iter().map(|(name, value)| format!("\"{}\":{}", name, value)).collect();
+                    writeln!(self.out, "{{\"step\":{},\"time\":{},\"id\":{},{}}}", self.steps, number(self.format, self.time), id, values.join(","))?;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn writeDiagnostics(&mut self, diagnostics: &Diagnostics) -> io::Result<()>
+    {
+        if !self.started && self.format == Format::Csv {
+            writeln!(self.out, "step,time,momentum_x,momentum_y,angular_momentum,kinetic,potential,total")?;
+        }
+        self.started = true;
+
+        let fields = [
+            ("momentum", vector(self.format, diagnostics.momentum.x(), diagnostics.momentum.y())),
+            ("angular_momentum", number(self.format, diagnostics.angular_momentum)),
+            ("kinetic", number(self.format, diagnostics.kinetic)),
+            ("potential", number(self.format, diagnostics.potential)),
+            ("total", number(self.format, diagnostics.total())),
+        ];
+        match self.format {
+            Format::Csv => {
+                let values: Vec<&str> = fields.iter().map(|(_, value)| value.as_str()).collect();
+                writeln!(self.out, "{},{},{}", self.steps, self.time, values.join(","))
+            },
+            Format::JsonLines => {
+                let values: Vec<String> = fields.iter().map(|(name, value)| format!("\"{}\":{}", name, value)).collect();
+                writeln!(self.out, "{{\"step\":{},\"time\":{},{}}}", self.steps, number(self.format, self.time), values.join(","))
+            },
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()>
+    {
+        self.out.flush()
+    }
+
+    pub fn into_inner(self) -> W
+    {
+        self.out
+    }
+}
+
+impl Exporter<BufWriter<File>>
+{
+    //Picks the format from the extension
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Exporter<BufWriter<File>>>
+    {
+        let path = path.as_ref();
+        let format = Format::from_path(path)
+            .ok_or(io::Error::new(io::ErrorKind::InvalidInput, format!("{} should end in .csv or .jsonl", path.display())))?;
+        Ok(Exporter::new(BufWriter::new(File::create(path)?), format))
+    }
+}
+
+//JSON has no NaN or infinity, so those become null
+fn number(format: Format, value: f64) -> String
+{
+    match format {
+        Format::JsonLines if !value.is_finite() => "null".to_string(),
+        _ => value.to_string(),
+    }
+}
+
+fn vector(format: Format, x: f64, y: f64) -> String
+{
+    match format {
+        Format::Csv => format!("{},{}", x, y),
+        Format::JsonLines => format!("[{},{}]", number(format, x), number(format, y)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec2::Vec2;
+    use crate::displayable::physics::{Shape, circle::Circle};
+    #[test]
+    fn writes_rows()
+    {
+        let mut world = World::new();
+        world.objects.push(Shape::Circle(Circle::new(Vec2::new(0.0, 0.0), 10.0)));
+        world.objects.push(Shape::Circle(Circle::new(Vec2::new(15.0, 0.0), 10.0)));
+        let mass = world.objects[0].mass();
+        world.objects[0].impulse(&Vec2::new(mass, 0.0));
+        let dt = crate::DT;
+
+        //The two start overlapping, so they touch in the first step
+        let mut json = Exporter::new(Vec::new(), Format::JsonLines).with_quantities(vec![Quantity::Velocity, Quantity::Contacts]);
+        world.step(dt);
+        json.sample(&world, dt).unwrap();
+        let json = String::from_utf8(json.into_inner()).unwrap();
+        assert_eq!(json.lines().count(), 2);
+        assert!(json.starts_with("{\"step\":1,"));
+        assert!(json.contains("\"velocity\":["));
+        assert!(!json.contains("position"));
+        assert!(json.lines().all(|line| line.ends_with("\"contacts\":1}")), "{}", json);
+
+        let mut csv = Exporter::new(Vec::new(), Format::Csv).with_interval(2);
+        for _ in 0..4 {
+            world.step(dt);
+            csv.sample(&world, dt).unwrap();
+        }
+        let csv = String::from_utf8(csv.into_inner()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "step,time,id,position_x,position_y,velocity_x,velocity_y,angular_velocity,kinetic_energy,contacts");
+        assert_eq!(lines.len(), 1 + 2*2);
+        assert!(lines[1].starts_with("2,"));
+    }
+}
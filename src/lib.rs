@@ -0,0 +1,28 @@
+#![allow(non_snake_case, dead_code)]
+
+pub mod vec2;
+pub mod rng;
+pub mod displayable;
+pub mod world;
+pub mod scene;
+pub mod checksum;
+pub mod replay;
+pub mod export;
+pub mod diagnostics;
+pub mod capture;
+
+extern crate assert_approx_eq;
+pub use assert_approx_eq::assert_approx_eq;
+
+extern crate sdl2;
+extern crate crossbeam;
+extern crate bresenham;
+
+use std::time::Duration;
+
+//Size of the view that Displayable lays its points out for, with the origin in the middle
+pub const WINDOW_DIMENSIONS: (u32, u32) = (1000, 1000);
+//The step the sandbox runs at, 60 times a second
+pub const DELTA_TIME: Duration = Duration::from_millis(1_000/60);
+//The same step in seconds, as World::step takes it
+pub const DT: f64 = DELTA_TIME.as_millis() as f64/1000.0;
@@ -1,15 +1,15 @@
 #![allow(non_snake_case, dead_code)]
 
-mod vec2;
-use crate::vec2::*;
+use physics_2d::{WINDOW_DIMENSIONS, DELTA_TIME};
+use physics_2d::vec2::*;
+use physics_2d::world::World;
+use physics_2d::scene::Scene;
+use physics_2d::replay::{Replay, Frame, Input, Click};
+use physics_2d::displayable::{Displayable, physics::{Shape, rect::Rect, circle::Circle}};
 
-mod displayable;
-use crate::displayable::{Displayable, physics::{detectIntersections, Shape, Physics, rect::Rect, circle::Circle}};
-
-use crate::displayable::UI::button::{Button, pauseButton::PauseButton, moveButton::MoveButton, rectButton::RectButton, circleButton::CircleButton};
-
-extern crate assert_approx_eq;
-pub use assert_approx_eq::assert_approx_eq;
+mod UI;
+mod sandbox;
+use crate::sandbox::{Sandbox, EXPORT_FILE, EXPORT_EVERY, CAPTURE_FILE, CAPTURE_FPS};
 
 extern crate bmp;
 
@@ -17,21 +17,21 @@ extern crate sdl2;
 use sdl2::pixels::Color;
 use sdl2::event::Event;
 use sdl2::video::Window;
-use sdl2::rect::Point;
 use sdl2::mouse::MouseButton;
+use sdl2::keyboard::Scancode;
 
-use std::time::Duration;
 
-extern crate crossbeam;
-use crossbeam::thread;
+extern crate bresenham;
 
-use std::sync::{Arc, Mutex};
+extern crate crossbeam;
 
-const DRAG: f64 = 1.0;
-const ELASTICITY: f64 = 1.0;
+use std::time::Instant;
 
-const WINDOW_DIMENSIONS: (u32, u32) = (1000, 1000);
-const DELTA_TIME: Duration = Duration::from_millis(1_000/60);
+const USAGE: &str = "usage: physics-2d [scene.ron] [--record FILE | --replay FILE [--headless]] [--export FILE.csv|FILE.jsonl] [--every N] [--diagnostics] [--capture FILE.png|FILE.bmp|FILE.gif] [--fps N]";
+//Opened when no scene is given on the command line
+const DEFAULT_SCENE: &str = "scenes/sandbox.ron";
+//Frames between writes of a recording, 10 seconds' worth
+const RECORD_EVERY: u64 = 600;
 
 #[derive(Debug)]
 pub enum ManipMode {
@@ -41,6 +41,7 @@ pub enum ManipMode {
     AngVelSetting(Shape, Vec2),
     Resizing(Shape, Vec2,  Shape),
     Rotating(Shape, Vec2, Shape),
+    Cutting(Vec2),
 }
 
 #[derive(Debug)]
@@ -67,238 +68,212 @@ fn init() -> (sdl2::render::Canvas<Window>, sdl2::EventPump)
     (canvas, event_pump)
 }
 
-fn main()
+struct Options
 {
-    let mut mode = Mode::Paused(ManipMode::Move);
-
-    let (mut canvas, mut event_pump) = init();
+    scene: String,
+    record: Option<String>,
+    replay: Option<String>,
+    headless: bool,
+    export: String,
+    every: usize,
+    diagnostics: bool,
+    capture: String,
+    fps: f64,
+}
 
-    let mut buttons: Vec<Box<dyn Button + Send + Sync>> = vec![
-        Box::new(PauseButton::new(Point::new(0, 0), Point::new(100, 100), "images/pause.bmp")),
-        Box::new(CircleButton::new(Point::new(100, 0), Point::new(200, 100), "images/circle.bmp")),
-        //Box::new(RectButton::new(Point::new(200, 0), Point::new(300, 100), "images/rect.bmp"))
-    ];
-
-    let mut objects: Vec<Shape> = vec![
-        Shape::Circle(Circle::new(Vec2::new(0.0, -300.0), 100.0)),
-        Shape::Circle(Circle::new(Vec2::new(-300.0, 0.0), 100.0)),
-        Shape::Circle(Circle::new(Vec2::new(300.0, 0.0), 100.0)),
-        Shape::Circle(Circle::new(Vec2::new(0.0, 300.0), 100.0)),
-        Shape::Circle(Circle::new(Vec2::new(300.0, 300.0), 100.0)),
-        Shape::Circle(Circle::new(Vec2::new(-300.0, 300.0), 100.0)),
-        Shape::Circle(Circle::new(Vec2::new(300.0, -300.0), 100.0)),
-        Shape::Circle(Circle::new(Vec2::new(-300.0, -300.0), 100.0)),
-        Shape::Rect(Rect::from_centre(Vec2::new(-(WINDOW_DIMENSIONS.0 as f64)/2.0, -(WINDOW_DIMENSIONS.1 as f64)/2.0), Vec2::new(1.0, WINDOW_DIMENSIONS.1 as f64 * 2.0-1.0), 0.0)),
-        Shape::Rect(Rect::from_centre(Vec2::new((WINDOW_DIMENSIONS.0 as f64)/2.0, (WINDOW_DIMENSIONS.1 as f64)/2.0), Vec2::new(1.0, WINDOW_DIMENSIONS.1 as f64 * 2.0-1.0), 0.0)),
-        Shape::Rect(Rect::from_centre(Vec2::new(-(WINDOW_DIMENSIONS.0 as f64)/2.0, (WINDOW_DIMENSIONS.1 as f64)/2.0), Vec2::new(WINDOW_DIMENSIONS.1 as f64 * 2.0-1.0, 1.0), 0.0)),
-        Shape::Rect(Rect::from_centre(Vec2::new((WINDOW_DIMENSIONS.0 as f64)/2.0, -(WINDOW_DIMENSIONS.1 as f64)/2.0), Vec2::new(WINDOW_DIMENSIONS.1 as f64 * 2.0-1.0, 1.0), 0.0)),
-    ];
-    objects[0].impulse(&Vec2::new(0.0, 1000.0));
-    objects[1].impulse(&Vec2::new(1000.0, 0.0));
-    objects[2].impulse(&Vec2::new(-1000.0, 0.0));
-    objects[3].impulse(&Vec2::new(0.0, -1000.0));
-    objects[4].impulse(&Vec2::new(-1000.0, -1000.0));
-    objects[5].impulse(&Vec2::new(1000.0, -1000.0));
-    objects[6].impulse(&Vec2::new(-1000.0, 1000.0));
-    objects[7].impulse(&Vec2::new(1000.0, 1000.0));
-
-    //let mut gravity = Vec2::new(0.0, -500.0);
-    let mut gravity = Vec2::zero();
-    
-    'running: loop {
-        let mouse_state = event_pump.mouse_state();
-        let mouse_pos = Vec2::from(Point::new(mouse_state.x(), mouse_state.y()));
-        let shift = event_pump.keyboard_state().is_scancode_pressed(sdl2::keyboard::Scancode::LShift)||event_pump.keyboard_state().is_scancode_pressed(sdl2::keyboard::Scancode::RShift);
-        let ctrl = event_pump.keyboard_state().is_scancode_pressed(sdl2::keyboard::Scancode::LCtrl)||event_pump.keyboard_state().is_scancode_pressed(sdl2::keyboard::Scancode::RCtrl);
-        
-        canvas.set_draw_color(Color::RGB(0, 0, 0));
-        canvas.clear();
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit{..} => break 'running,
-                Event::MouseButtonDown{x, y, mouse_btn, ..} => {
-                    for button in &mut buttons {
-                        if button.in_bounds(Point::new(x, y)) {
-                            button.click_down(&mut mode, &objects);
-                        }
-                    }
-                    match mode {
-                        Mode::Paused(ManipMode::Move) => {
-                            let click = Vec2::from(Point::new(x, y));
-                            let mut carried: Option<(usize, Vec2)> = None;
-                            for (i, shape) in objects.iter().enumerate() {
-                                if shape.pointIn(&click) {
-                                    carried = Some((i, click-shape.position()));
-                                    break;
-                                }
-                            }
-                            if let Some((i, position)) = carried {
-                                match mouse_btn {
-                                    MouseButton::Left if shift => {
-                                        let object = objects.remove(i);
-                                        let archive = object.clone();
-                                        mode = Mode::Paused(ManipMode::Resizing(object, position, archive));
-                                    },
-                                    MouseButton::Left if ctrl => {
-                                        let object = objects.remove(i);
-                                        mode = Mode::Paused(ManipMode::VelSetting(object, position));
-                                    },
-                                    MouseButton::Left => {
-                                        let object = objects.remove(i);
-                                        mode = Mode::Paused(ManipMode::Carrying(object, position));
-                                    },
-                                    MouseButton::Right if shift => {
-                                        objects.remove(i);
-                                    },
-                                    MouseButton::Right if ctrl => {
-                                        let object = objects.remove(i);
-                                        mode = Mode::Paused(ManipMode::AngVelSetting(object, position));
-                                    },
-                                    MouseButton::Right => {
-                                        let object = objects.remove(i);
-                                        let archive = object.clone();
-                                        mode = Mode::Paused(ManipMode::Rotating(object, position, archive));
-                                    },
-                                    _ => (),
-                                }
-                            }
-                        },
-
-                        _ => (),
-                    }
-                },
-
-                Event::MouseButtonUp{..} => {
-                    for button in &mut buttons {
-                        if button.clicked() {
-                            button.click_up(&objects);
-                        }
-                    }
-
-                    match mode {
-                        Mode::Paused(ManipMode::Carrying(shape, _)) => {
-                            objects.push(shape);
-                            mode = Mode::Paused(ManipMode::Move);
-                        },
-
-                        Mode::Paused(ManipMode::VelSetting(shape, _)) => {
-                            objects.push(shape);
-                            mode = Mode::Paused(ManipMode::Move);
-                        },
-
-                        Mode::Paused(ManipMode::AngVelSetting(shape, _)) => {
-                            objects.push(shape);
-                            mode = Mode::Paused(ManipMode::Move);
-                        },
-
-                        Mode::Paused(ManipMode::Resizing(shape, _, _)) => {
-                            objects.push(shape);
-                            mode = Mode::Paused(ManipMode::Move);
-                        },
-
-                        Mode::Paused(ManipMode::Rotating(shape, _, _)) => {
-                            objects.push(shape);
-                            mode = Mode::Paused(ManipMode::Move);
-                        },
-
-                        _ => (),
-                    }
-                },
-
-                _ => {}
-            }
+fn parseArgs(args: &[String]) -> Result<Options, String>
+{
+    let mut scene = None;
+    let (mut record, mut replay) = (None, None);
+    let mut headless = false;
+    let mut export = EXPORT_FILE.to_string();
+    let mut every = EXPORT_EVERY;
+    let mut diagnostics = false;
+    let mut capture = CAPTURE_FILE.to_string();
+    let mut fps = CAPTURE_FPS;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().cloned().ok_or(format!("{} needs a value", name));
+        match arg.as_str() {
+            "--record" => record = Some(value("--record")?),
+            "--replay" => replay = Some(value("--replay")?),
+            "--headless" => headless = true,
+            "--export" => export = value("--export")?,
+            "--diagnostics" => diagnostics = true,
+            "--capture" => capture = value("--capture")?,
+            "--fps" => fps = value("--fps")?.parse::<f64>().map_err(|e| format!("bad --fps: {}", e))?,
+            "--every" => every = value("--every")?.parse::<usize>().map_err(|e| format!("bad --every: {}", e))?,
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path if scene.is_none() => scene = Some(path.to_string()),
+            extra => return Err(format!("unexpected argument {}", extra)),
         }
+    }
 
-        let points = Arc::new(Mutex::new(Vec::new()));
+    if record.is_some() && replay.is_some() {
+        return Err("give --record or --replay, not both".to_string());
+    }
+    if headless && replay.is_none() {
+        return Err("--headless only runs replays".to_string());
+    }
+    if every == 0 {
+        return Err("--every must be at least 1".to_string());
+    }
+    if fps <= 0.0 {
+        return Err("--fps must be positive".to_string());
+    }
+    Ok(Options{scene: scene.unwrap_or(DEFAULT_SCENE.to_string()), record, replay, headless, export, every, diagnostics, capture, fps})
+}
 
-        
-        match mode {
-            Mode::Paused(ManipMode::Carrying(ref mut shape, ref grabPos)) => {
-                let p = &mut shape.display();
-                points.lock().unwrap().append(p);
-    
-                //println!("{:?} {:?}", grabPos, mouse_pos);
-                shape.translateTo(&(mouse_pos-*grabPos));
+//Reads a frame's input from SDL, or None when the window is closed
+fn readFrame(event_pump: &mut sdl2::EventPump, number: u64, time: f64) -> Option<Frame>
+{
+    let mut frame = Frame::new(number, time);
+    let mouse_state = event_pump.mouse_state();
+    frame.mouse = (mouse_state.x(), mouse_state.y());
+    let keyboard = event_pump.keyboard_state();
+    frame.shift = keyboard.is_scancode_pressed(Scancode::LShift)||keyboard.is_scancode_pressed(Scancode::RShift);
+    frame.ctrl = keyboard.is_scancode_pressed(Scancode::LCtrl)||keyboard.is_scancode_pressed(Scancode::RCtrl);
+    frame.rewinding = keyboard.is_scancode_pressed(Scancode::R);
+
+    for event in event_pump.poll_iter() {
+        match event {
+            Event::Quit{..} => return None,
+            Event::MouseButtonDown{x, y, mouse_btn, ..} => {
+                let button = match mouse_btn {
+                    MouseButton::Left => Click::Left,
+                    MouseButton::Middle => Click::Middle,
+                    MouseButton::Right => Click::Right,
+                    _ => continue,
+                };
+                frame.inputs.push(Input::MouseDown{x, y, button});
             },
+            Event::MouseButtonUp{x, y, ..} => frame.inputs.push(Input::MouseUp{x, y}),
+            Event::KeyDown{keycode: Some(key), repeat: false, ..} => frame.inputs.push(Input::Key(key as i32)),
+            Event::KeyDown{keycode: Some(key), repeat: true, ..} => frame.inputs.push(Input::Repeat(key as i32)),
+            _ => {}
+        }
+    }
+    Some(frame)
+}
 
-            Mode::Paused(ManipMode::VelSetting(ref mut shape, ref grabPos)) => {
-                let p = &mut shape.display();
-                points.lock().unwrap().append(p);
-    
-                //println!("{:?} {:?}", grabPos, mouse_pos);
-                shape.impulse(&(((mouse_pos-shape.position())-*grabPos)-shape.velocity()));
-            },
-    
-            Mode::Paused(ManipMode::AngVelSetting(ref mut shape, ref grabPos)) => {
-                let p = &mut shape.display();
-                points.lock().unwrap().append(p);
-    
-                //println!("{:?} {:?}", grabPos, mouse_pos);
-                let (_, angle) = (mouse_pos-shape.position()).polar();
-                let (_, newAngle) = grabPos.polar();
-                shape.angular_impulse((newAngle-angle)-shape.angular_velocity());
-            },
+//Runs a recorded session through to the end without a window and prints where it ended up
+fn replayHeadless(replay: &Replay, mut sandbox: Sandbox)
+{
+    for frame in replay.playback() {
+        sandbox.advance(&frame, false);
+    }
+    sandbox.finish();
+    println!("# frames {}", replay.length);
+    println!("# checksum {:016x}", sandbox.world().checksum());
+}
 
-            Mode::Paused(ManipMode::Resizing(ref mut shape, ref click, ref archive)) => {
-                let p = &mut shape.display();
-                points.lock().unwrap().append(p);
-    
-                shape.resize(click, &(mouse_pos-shape.position()), archive);
-            },
+//The session being recorded and where it goes. It is written out every so often while it runs, and
+//again when dropped, which a panic does too, so a crash loses at most the last few seconds
+struct Recorder
+{
+    replay: Replay,
+    path: String,
+}
 
-            Mode::Paused(ManipMode::Rotating(ref mut shape, ref click, ref archive)) => {
-                let p = &mut shape.display();
-                points.lock().unwrap().append(p);
-    
-                shape.rotate(click, &(mouse_pos-shape.position()), archive);
-            },
-            _ => (),
-        }
+impl Recorder
+{
+    fn new(scene: Scene, path: &str) -> Recorder
+    {
+        Recorder{replay: Replay::new(scene), path: path.to_string()}
+    }
 
-        if let Mode::Unpaused = mode {
-            let mut archive = objects.clone();
-            thread::scope( |s| {
-                for object in objects.iter_mut() {
-                    s.spawn(|_| {
-                        object.integrate(&gravity);
-                    });
-                }
-            }).unwrap();
-            let intersecting = detectIntersections(&objects);
-            if intersecting.len() > 0 {
-                for (i1, i2) in intersecting {
-                    let (a, b) = archive.split_at_mut(i2);
-                    let shape1 = &mut a[i1];
-                    let archiveShape1 = shape1.clone();
-                    let shape2 = &mut b[0];
-                    if let Shape::Circle(_) = shape1 {
-                        shape1.bounce(&shape2);
-                    }
-                    if let Shape::Circle(_) = shape2 {
-                        shape2.bounce(&archiveShape1);
-                    }
-                }
-                objects = archive;
+    //Takes the frame, and the scenes the sandbox loaded while running it
+    fn record(&mut self, frame: Frame, sandbox: &mut Sandbox)
+    {
+        self.replay.record(frame);
+        self.replay.loads.extend(sandbox.takeLoaded());
+        if self.replay.length.is_multiple_of(RECORD_EVERY) {
+            if let Err(error) = self.replay.save(&self.path) {
+                eprintln!("Could not save the recording: {}", error);
             }
         }
+    }
+}
 
-        thread::scope( |s| {
-            for object in &objects {
-                s.spawn(|_| {
-                    let p = &mut object.display();
-                    points.lock().unwrap().append(p);
-                });
-            }
-            for UI in &buttons {
-                s.spawn(|_| {
-                    let p = &mut UI.display();
-                    points.lock().unwrap().append(p);
-                });
-            }
-        }).unwrap();
+impl Drop for Recorder
+{
+    fn drop(&mut self)
+    {
+        match self.replay.save(&self.path) {
+            Ok(()) => println!("Recorded {} frames to {}", self.replay.length, self.path),
+            Err(error) => eprintln!("Could not save the recording: {}", error),
+        }
+    }
+}
 
-        let points = points.lock().unwrap();
+fn main()
+{
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parseArgs(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            std::process::exit(2);
+        },
+    };
+
+    //A replay brings the scene it was recorded from
+    let loaded = match &options.replay {
+        Some(path) => Replay::load(path).map(|replay| (replay.scene.clone(), Some(replay))),
+        None => Scene::load(&options.scene).map(|scene| (scene, None)),
+    };
+    let (scene, replay, world) = match loaded.and_then(|(scene, replay)| scene.build().map(|world| (scene, replay, world))) {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        },
+    };
+    let mut sandbox = Sandbox::new(world)
+        .with_export(&options.export, options.every, options.diagnostics)
+        .with_capture(&options.capture, options.fps);
+    if let Some(replay) = &replay {
+        sandbox = sandbox.with_replay(replay);
+    }
+    else if options.record.is_some() {
+        sandbox = sandbox.with_recording();
+    }
 
+    if let (Some(replay), true) = (&replay, options.headless) {
+        replayHeadless(replay, sandbox);
+        return;
+    }
+
+    let (mut canvas, mut event_pump) = init();
+    let mut recording = options.record.as_ref().map(|path| Recorder::new(scene, path));
+    let mut playback = replay.as_ref().map(|replay| replay.playback());
+    let start = Instant::now();
+
+    for number in 0.. {
+        let frame = match &mut playback {
+            //While a replay runs, closing the window is the only input listened to
+            Some(playback) => {
+                if event_pump.poll_iter().any(|event| matches!(event, Event::Quit{..})) {
+                    break;
+                }
+                match playback.next() {
+                    Some(frame) => frame,
+                    None => break,
+                }
+            },
+            None => match readFrame(&mut event_pump, number, start.elapsed().as_secs_f64()) {
+                Some(frame) => frame,
+                None => break,
+            },
+        };
+        let points = sandbox.advance(&frame, true);
+        if let Some(recording) = &mut recording {
+            recording.record(frame, &mut sandbox);
+        }
+
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        canvas.clear();
         canvas.set_draw_color(points[0].1);
         let mut points = points.iter().peekable();
         while let Some((point, color)) = points.next() {
@@ -314,4 +289,10 @@ fn main()
         canvas.present();
         std::thread::sleep(DELTA_TIME);
     }
+
+    sandbox.finish();
+    drop(recording);
+    if replay.is_some() {
+        println!("# checksum {:016x}", sandbox.world().checksum());
+    }
 }
@@ -0,0 +1,206 @@
+use crate::scene::{Scene, SceneError};
+
+use serde::{Serialize, Deserialize};
+
+use std::fs;
+use std::path::Path;
+
+//Replays share the scene format's versioning rules
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Click
+{
+    Left,
+    Middle,
+    Right,
+}
+
+//Something the user did during a frame, in window coordinates. Keys are stored as SDL keycodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Input
+{
+    MouseDown{x: i32, y: i32, button: Click},
+    MouseUp{x: i32, y: i32},
+    Key(i32),
+    //A key held down long enough for the system to send it again
+    Repeat(i32),
+}
+
+//Everything the sandbox reads from the user in one frame. The mouse, modifiers and R are sampled
+//once at the start of the frame, which is what carrying, velocity setting, resizing and rotating follow
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Frame
+{
+    pub frame: u64,
+    //Seconds since recording started, so a report can say when something happened
+    pub time: f64,
+    pub mouse: (i32, i32),
+    #[serde(default)]
+    pub shift: bool,
+    #[serde(default)]
+    pub ctrl: bool,
+    #[serde(default)]
+    pub rewinding: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<Input>,
+}
+
+impl Frame
+{
+    pub fn new(frame: u64, time: f64) -> Frame
+    {
+        Frame{frame, time, mouse: (0, 0), shift: false, ctrl: false, rewinding: false, inputs: Vec::new()}
+    }
+
+    //Whether the frame reads the same as the one before, so it need not be stored
+    fn quiet(&self, previous: &Frame) -> bool
+    {
+        self.inputs.is_empty() && (self.mouse, self.shift, self.ctrl, self.rewinding) == (previous.mouse, previous.shift, previous.ctrl, previous.rewinding)
+    }
+}
+
+//A session: the scene it started from and the input of every frame. Frames where nothing changed
+//are left out, playback fills them in. Replaying steps the world deterministically, so the same
+//file always gives the same world
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay
+{
+    pub version: u32,
+    pub scene: Scene,
+    //How many frames the session ran for
+    pub length: u64,
+    pub frames: Vec<Frame>,
+    //What each load during the session read, in order, None where it failed. Playback answers
+    //loads from here rather than the file, which may have changed since
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub loads: Vec<Option<Scene>>,
+}
+
+impl Replay
+{
+    pub fn new(mut scene: Scene) -> Replay
+    {
+        scene.settings.deterministic = true;
+        Replay{version: VERSION, scene, length: 0, frames: Vec::new(), loads: Vec::new()}
+    }
+
+    pub fn record(&mut self, frame: Frame)
+    {
+        self.length = frame.frame+1;
+        let quiet = match self.frames.last() {
+            Some(previous) => frame.quiet(previous),
+            None => frame.quiet(&Frame::new(0, 0.0)),
+        };
+        if !quiet {
+            self.frames.push(frame);
+        }
+    }
+
+    //Every frame of the session in order, the quiet ones included
+    pub fn playback(&self) -> Playback<'_>
+    {
+        Playback{replay: self, number: 0, next: 0, current: Frame::new(0, 0.0)}
+    }
+
+    pub fn parse(text: &str) -> Result<Replay, SceneError>
+    {
+        let replay: Replay = ron::from_str(text).map_err(|error| SceneError::Parse{path: None, line: error.position.line, column: error.position.col, message: error.code.to_string()})?;
+        if replay.version > VERSION {
+            return Err(SceneError::Version{found: replay.version, supported: VERSION});
+        }
+        Ok(replay)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Replay, SceneError>
+    {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|error| SceneError::Io{path: path.to_path_buf(), error})?;
+        Replay::parse(&text).map_err(|error| match error {
+            SceneError::Parse{line, column, message, ..} => SceneError::Parse{path: Some(path.to_path_buf()), line, column, message},
+            error => error,
+        })
+    }
+
+    pub fn write(&self) -> Result<String, SceneError>
+    {
+        let config = ron::ser::PrettyConfig::new().depth_limit(4);
+        ron::ser::to_string_pretty(self, config).map_err(|error| SceneError::Invalid(error.to_string()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneError>
+    {
+        let path = path.as_ref();
+        fs::write(path, self.write()?).map_err(|error| SceneError::Io{path: path.to_path_buf(), error})
+    }
+}
+
+pub struct Playback<'a>
+{
+    replay: &'a Replay,
+    number: u64,
+    next: usize,
+    current: Frame,
+}
+
+impl<'a> Iterator for Playback<'a>
+{
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame>
+    {
+        let number = self.number;
+        if number >= self.replay.length {
+            return None;
+        }
+        match self.replay.frames.get(self.next) {
+            Some(frame) if frame.frame == number => {
+                self.current = frame.clone();
+                self.next += 1;
+            },
+            _ => {
+                self.current.frame = number;
+                self.current.inputs.clear();
+            },
+        }
+        self.number += 1;
+        Some(self.current.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn plays_back_what_was_recorded()
+    {
+        let mut frames = Vec::new();
+        for i in 0..20 {
+            let mut frame = Frame::new(i, i as f64/60.0);
+            frame.mouse = if i < 5 { (0, 0) } else { (100, 10*(i as i32/8)) };
+            frame.ctrl = i >= 12;
+            if i == 6 {
+                frame.inputs.push(Input::MouseDown{x: 100, y: 0, button: Click::Left});
+            }
+            if i == 9 {
+                frame.inputs.push(Input::MouseUp{x: 100, y: 10});
+                frame.inputs.push(Input::Key(119));
+                frame.inputs.push(Input::Repeat(119));
+            }
+            frames.push(frame);
+        }
+        let mut replay = Replay::new(Scene::parse("(version: 1)").unwrap());
+        for frame in &frames {
+            replay.record(frame.clone());
+        }
+        assert!(replay.frames.len() < frames.len());
+
+        let loaded = Replay::parse(&replay.write().unwrap()).unwrap();
+        assert!(loaded.scene.settings.deterministic);
+        let played: Vec<Frame> = loaded.playback().collect();
+        assert_eq!(played.len(), frames.len());
+        for (a, b) in frames.iter().zip(&played) {
+            assert_eq!((a.frame, a.mouse, a.ctrl, &a.inputs), (b.frame, b.mouse, b.ctrl, &b.inputs));
+        }
+    }
+}
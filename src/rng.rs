@@ -0,0 +1,54 @@
+//Mixed into the seed so small seeds still start from a well spread state
+const MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+
+//Small xorshift generator. Seeded explicitly so that anything random in a scene can be replayed
+#[derive(Debug, Clone, Copy)]
+pub struct Rng
+{
+    state: u64,
+}
+
+impl Rng
+{
+    pub fn new(seed: u64) -> Rng
+    {
+        //A zero state would only ever produce zeros, so the one seed that mixes to it gets another
+        let state = seed ^ MIX;
+        Rng{state: if state == 0 { MIX } else { state }}
+    }
+
+    pub fn next_u64(&mut self) -> u64
+    {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    //Uniform in [0, 1)
+    pub fn float(&mut self) -> f64
+    {
+        (self.next_u64() >> 11) as f64/(1u64 << 53) as f64
+    }
+
+    //Uniform in [low, high)
+    pub fn range(&mut self, low: f64, high: f64) -> f64
+    {
+        low+(high-low)*self.float()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn every_seed_produces_numbers()
+    {
+        for seed in [0, 1, MIX, u64::MAX] {
+            let mut rng = Rng::new(seed);
+            assert!((0..4).map(|_| rng.next_u64()).any(|x| x != 0), "seed {:x}", seed);
+        }
+    }
+}
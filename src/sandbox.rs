@@ -0,0 +1,713 @@
+use crate::{Mode, ManipMode};
+use crate::UI::button::{Button, pauseButton::PauseButton, circleButton::CircleButton, saveButton::SaveButton, loadButton::LoadButton};
+use crate::UI::message::Message;
+use crate::UI::overlay::Overlay;
+use crate::UI::storage::Storage;
+
+use physics_2d::DT;
+use physics_2d::vec2::*;
+use physics_2d::world::{World, Snapshot};
+use physics_2d::scene::Scene;
+use physics_2d::replay::{Replay, Frame, Input, Click};
+use physics_2d::export::Exporter;
+use physics_2d::diagnostics::Diagnostics;
+use physics_2d::capture::{Capture, Image};
+use physics_2d::displayable::{Displayable, physics::{detectIntersections, Shape, Physics, fluid::FluidRender, forceField::{ForceField, FieldKind, FieldArea, Falloff}, nbody::NBody, sleep::wakeIsland, drag::{Drag, DragModel}}};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+use sdl2::keyboard::Keycode;
+
+use bresenham::Bresenham;
+
+use crossbeam::thread;
+
+use std::sync::{Arc, Mutex};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufWriter;
+
+//Where the save and load buttons keep the scene
+const SAVE_FILE: &str = "scenes/saved.ron";
+//Where E writes body state to, and how many steps apart the rows are, unless told otherwise
+pub const EXPORT_FILE: &str = "export.csv";
+pub const EXPORT_EVERY: usize = 6;
+//Where V records what is on screen to, and how many frames a second it keeps
+pub const CAPTURE_FILE: &str = "capture.gif";
+pub const CAPTURE_FPS: f64 = 30.0;
+//Edits that can be undone, and frames that can be rewound, 10 seconds' worth
+const UNDO_LIMIT: usize = 100;
+const REWIND_FRAMES: usize = 600;
+const WATER_BLOCK: f64 = 80.0;
+const FIELD_RADIUS: f64 = 150.0;
+const GRAVITATIONAL_CONSTANT: f64 = 1000.0;
+const BARNES_HUT_THETA: f64 = 0.5;
+const BREAK_IMPULSE: f64 = 1000000.0;
+const CHARGE: f64 = 40000.0;
+const DIPOLE: f64 = 5000000.0;
+const MAGNETIC_FIELD: f64 = 1.0;
+
+//Keeps the world as it was before an edit, dropping the oldest once there are too many
+fn remember(undo: &mut VecDeque<Snapshot>, world: &World)
+{
+    undo.push_back(world.snapshot());
+    if undo.len() > UNDO_LIMIT {
+        undo.pop_front();
+    }
+}
+
+//Everything the user can change, driven a frame at a time by input from the window or a replay
+pub struct Sandbox
+{
+    mode: Mode,
+    world: World,
+    buttons: Vec<Box<dyn Button + Send + Sync>>,
+    showFieldLines: bool,
+    message: Option<Message>,
+    undo: VecDeque<Snapshot>,
+    history: VecDeque<Snapshot>,
+    //Set while recording or replaying, and kept on through loads, so replays come out the same
+    deterministic: bool,
+    //Where the save and load buttons, and Ctrl+S and Ctrl+L, keep the scene
+    storage: Storage,
+    //Some while E has body state being written out
+    exporter: Option<Exporter<BufWriter<File>>>,
+    exportPath: String,
+    exportEvery: usize,
+    //Whether E writes the world's momentum and energy rather than each body
+    exportDiagnostics: bool,
+    //Some while I has the momentum and energy graphs showing
+    overlay: Option<Overlay>,
+    //Some while V has the screen being recorded
+    capture: Option<Capture>,
+    capturePath: String,
+    captureFps: f64,
+}
+
+impl Sandbox
+{
+    pub fn new(world: World) -> Sandbox
+    {
+        let buttons: Vec<Box<dyn Button + Send + Sync>> = vec![
+            Box::new(PauseButton::new(Point::new(0, 0), Point::new(100, 100), "images/pause.bmp")),
+            Box::new(CircleButton::new(Point::new(100, 0), Point::new(200, 100), "images/circle.bmp")),
+            Box::new(SaveButton::new(Point::new(200, 0), Point::new(300, 100), "images/save.bmp")),
+            Box::new(LoadButton::new(Point::new(300, 0), Point::new(400, 100), "images/load.bmp")),
+            //Box::new(RectButton::new(Point::new(400, 0), Point::new(500, 100), "images/rect.bmp"))
+        ];
+        Sandbox{
+            mode: Mode::Paused(ManipMode::Move),
+            world,
+            buttons,
+            showFieldLines: false,
+            message: None,
+            undo: VecDeque::new(),
+            history: VecDeque::new(),
+            deterministic: false,
+            storage: Storage::new(SAVE_FILE),
+            exporter: None,
+            exportPath: EXPORT_FILE.to_string(),
+            exportEvery: EXPORT_EVERY,
+            exportDiagnostics: false,
+            overlay: None,
+            capture: None,
+            capturePath: CAPTURE_FILE.to_string(),
+            captureFps: CAPTURE_FPS,
+        }
+    }
+
+    //Where E writes to, how many steps apart, and whether the world's totals rather than each body
+    pub fn with_export(mut self, path: &str, every: usize, diagnostics: bool) -> Self
+    {
+        self.exportPath = path.to_string();
+        self.exportEvery = every;
+        self.exportDiagnostics = diagnostics;
+        self
+    }
+
+    pub fn with_capture(mut self, path: &str, fps: f64) -> Self
+    {
+        self.capturePath = path.to_string();
+        self.captureFps = fps;
+        self
+    }
+
+    pub fn with_save_file(mut self, path: &str) -> Self
+    {
+        self.storage.setPath(path);
+        self
+    }
+
+    //Steps deterministically and keeps the scenes loaded, for takeLoaded to hand to the recording
+    pub fn with_recording(mut self) -> Self
+    {
+        self.deterministic = true;
+        self.world.settings.deterministic = true;
+        self.storage.record();
+        self
+    }
+
+    //Steps deterministically, never saves, and loads the scenes the recording loaded
+    pub fn with_replay(mut self, replay: &Replay) -> Self
+    {
+        self.deterministic = true;
+        self.world.settings.deterministic = true;
+        self.storage.replay(&replay.loads);
+        self
+    }
+
+    #[inline]
+    pub fn world(&self) -> &World
+    {
+        &self.world
+    }
+
+    //The scenes loaded since this was last called, while recording
+    pub fn takeLoaded(&mut self) -> Vec<Option<Scene>>
+    {
+        self.storage.takeLoaded()
+    }
+
+    //The mouse and modifiers come from the frame, as they were when it started
+    fn handle(&mut self, input: &Input, frame: &Frame)
+    {
+        match *input {
+            Input::MouseDown{x, y, button} => self.mouseDown(x, y, button, frame.shift, frame.ctrl),
+            Input::MouseUp{x, y} => self.mouseUp(x, y),
+            Input::Key(key) | Input::Repeat(key) => {
+                if let Some(key) = Keycode::from_i32(key) {
+                    let mouse_pos = Vec2::from(Point::new(frame.mouse.0, frame.mouse.1));
+                    self.keyDown(key, mouse_pos, frame.ctrl, matches!(input, Input::Repeat(_)));
+                }
+            },
+        }
+        self.world.settings.deterministic |= self.deterministic;
+    }
+
+    fn mouseDown(&mut self, x: i32, y: i32, mouse_btn: Click, shift: bool, ctrl: bool)
+    {
+        let world = &mut self.world;
+        for button in &mut self.buttons {
+            if button.in_bounds(Point::new(x, y)) {
+                if let Some(m) = button.click_down(&mut self.mode, world, &mut self.storage) {
+                    self.message = Some(m);
+                }
+                //A shape button hands over a new shape to place. It only joins the world when
+                //dropped, so the world is still as it was before the edit
+                if let Mode::Paused(ManipMode::Carrying(..)) = self.mode {
+                    remember(&mut self.undo, world);
+                }
+                //Whatever is under the button stays where it is
+                return;
+            }
+        }
+        if let Mode::Paused(ManipMode::Move) = self.mode {
+            let click = Vec2::from(Point::new(x, y));
+            let mut carried: Option<(usize, Vec2)> = None;
+            for (i, shape) in world.objects.iter().enumerate() {
+                if shape.pointIn(&click) {
+                    carried = Some((i, click-shape.position()));
+                    break;
+                }
+            }
+            if let (Some((i, position)), Click::Left | Click::Right) = (carried, mouse_btn) {
+                remember(&mut self.undo, world);
+                let contacts = detectIntersections(&world.objects);
+                wakeIsland(&mut world.objects, &contacts, i);
+                match mouse_btn {
+                    Click::Left if shift => {
+                        let object = world.objects.remove(i);
+                        let archive = object.clone();
+                        self.mode = Mode::Paused(ManipMode::Resizing(object, position, archive));
+                    },
+                    Click::Left if ctrl => {
+                        let object = world.objects.remove(i);
+                        self.mode = Mode::Paused(ManipMode::VelSetting(object, position));
+                    },
+                    Click::Left => {
+                        let object = world.objects.remove(i);
+                        self.mode = Mode::Paused(ManipMode::Carrying(object, position));
+                    },
+                    Click::Right if shift => {
+                        world.objects.remove(i);
+                    },
+                    Click::Right if ctrl => {
+                        let object = world.objects.remove(i);
+                        self.mode = Mode::Paused(ManipMode::AngVelSetting(object, position));
+                    },
+                    Click::Right => {
+                        let object = world.objects.remove(i);
+                        let archive = object.clone();
+                        self.mode = Mode::Paused(ManipMode::Rotating(object, position, archive));
+                    },
+                    _ => (),
+                }
+            }
+            else if let (None, Click::Middle) = (carried, mouse_btn) {
+                remember(&mut self.undo, world);
+                self.mode = Mode::Paused(ManipMode::Cutting(click));
+            }
+        }
+    }
+
+    fn keyDown(&mut self, key: Keycode, mouse_pos: Vec2, ctrl: bool, repeat: bool)
+    {
+        let world = &mut self.world;
+        //Edits made while paused can be undone, each arm that changes the world remembers it first.
+        //A held key only counts once, or its repeats would fill the undo list
+        let undoable = matches!(self.mode, Mode::Paused(_)) && !repeat;
+        let under = world.objects.iter().position(|o| o.pointIn(&mouse_pos));
+        let undo = &mut self.undo;
+        let mut edit = |world: &World| if undoable { remember(undo, world) };
+        match (key, &self.mode) {
+            (Keycode::Z, Mode::Paused(ManipMode::Move)) if ctrl => {
+                self.message = Some(match self.undo.pop_back() {
+                    Some(snapshot) => {
+                        world.restore(&snapshot);
+                        Message::success("Undone".to_string())
+                    },
+                    None => Message::failure("Nothing to undo".to_string()),
+                });
+            },
+            (Keycode::S, _) if ctrl => {
+                self.message = Some(self.storage.save(world));
+            },
+            (Keycode::L, _) if ctrl => {
+                self.message = Some(self.storage.load(world, &mut self.mode));
+            },
+            //Pours a block of water in at the mouse
+            (Keycode::W, Mode::Paused(ManipMode::Move)) => {
+                edit(world);
+                world.fluid.addBlock(mouse_pos-Vec2::new(WATER_BLOCK, WATER_BLOCK)/2.0, Vec2::new(WATER_BLOCK, WATER_BLOCK));
+            },
+            //Number keys place force fields at the mouse, X removes them
+            (Keycode::Num1, Mode::Paused(ManipMode::Move)) => {
+                edit(world);
+                world.fields.push(ForceField::new(mouse_pos, FieldKind::Uniform(Vec2::new(0.0, 500.0)), FieldArea::Rect(Vec2::new(FIELD_RADIUS, FIELD_RADIUS*2.0))));
+            },
+            (Keycode::Num2, Mode::Paused(ManipMode::Move)) => {
+                edit(world);
+                world.fields.push(ForceField::new(mouse_pos, FieldKind::Attractor{strength: 1000.0, falloff: Falloff::Linear}, FieldArea::Circle(FIELD_RADIUS)));
+            },
+            (Keycode::Num3, Mode::Paused(ManipMode::Move)) => {
+                edit(world);
+                world.fields.push(ForceField::new(mouse_pos, FieldKind::Attractor{strength: -1000.0, falloff: Falloff::Linear}, FieldArea::Circle(FIELD_RADIUS)));
+            },
+            (Keycode::Num4, Mode::Paused(ManipMode::Move)) => {
+                edit(world);
+                world.fields.push(ForceField::new(mouse_pos, FieldKind::Vortex{strength: 1000.0, falloff: Falloff::Linear}, FieldArea::Circle(FIELD_RADIUS)));
+            },
+            (Keycode::Num5, Mode::Paused(ManipMode::Move)) => {
+                edit(world);
+                world.fields.push(ForceField::new(mouse_pos, FieldKind::Explosion{impulse: 1500.0}, FieldArea::Circle(FIELD_RADIUS*2.0)));
+            },
+            (Keycode::Num6, Mode::Paused(ManipMode::Move)) => {
+                edit(world);
+                world.fields.push(ForceField::new(mouse_pos, FieldKind::Wind{velocity: Vec2::new(400.0, 0.0), turbulence: 200.0}, FieldArea::Rect(Vec2::new(FIELD_RADIUS*2.0, FIELD_RADIUS))));
+            },
+            //Turns the body under the mouse into a sensor, or back
+            (Keycode::S, Mode::Paused(ManipMode::Move)) => {
+                if let Some(i) = under {
+                    edit(world);
+                    let body = world.objects[i].mutBody();
+                    body.sensor = !body.sensor;
+                }
+            },
+            //Steps the rect under the mouse through the surface types
+            (Keycode::F, Mode::Paused(ManipMode::Move)) => {
+                if let Some(i) = under.filter(|&i| matches!(world.objects[i], Shape::Rect(_))) {
+                    edit(world);
+                    if let Shape::Rect(rect) = &mut world.objects[i] {
+                        rect.setSurface(rect.surface().next());
+                    }
+                }
+            },
+            //Makes the body under the mouse breakable, or not
+            (Keycode::K, Mode::Paused(ManipMode::Move)) => {
+                if let Some(i) = under {
+                    edit(world);
+                    let body = world.objects[i].mutBody();
+                    body.break_impulse = match body.break_impulse {
+                        Some(_) => None,
+                        None => Some(BREAK_IMPULSE),
+                    };
+                }
+            },
+            //Charges the body under the mouse positively, then negatively, then not at all
+            (Keycode::C, Mode::Paused(ManipMode::Move)) => {
+                if let Some(i) = under {
+                    edit(world);
+                    let body = world.objects[i].mutBody();
+                    body.charge = match body.charge {
+                        c if c > 0.0 => -CHARGE,
+                        c if c < 0.0 => 0.0,
+                        _ => CHARGE,
+                    };
+                }
+            },
+            //Gives the body under the mouse a magnetic dipole pointing right, or takes it away
+            (Keycode::G, Mode::Paused(ManipMode::Move)) => {
+                if let Some(i) = under {
+                    edit(world);
+                    let body = world.objects[i].mutBody();
+                    body.dipole = if body.dipole == Vec2::zero() { Vec2::new(DIPOLE, 0.0) } else { Vec2::zero() };
+                }
+            },
+            //Steps the body under the mouse through no drag, quadratic drag and linear drag.
+            //Rects get lift too, so they flutter as they fall
+            (Keycode::D, Mode::Paused(ManipMode::Move)) => {
+                if let Some(i) = under {
+                    edit(world);
+                    let body = world.objects[i].mutBody();
+                    body.drag = match body.drag.model() {
+                        DragModel::None => Drag::quadratic(1.0).with_lift(1.0).with_angular(6.0),
+                        DragModel::Quadratic(_) => Drag::linear(0.5).with_angular(1.0),
+                        DragModel::Linear(_) => Drag::none(),
+                    };
+                }
+            },
+            (Keycode::X, Mode::Paused(ManipMode::Move)) if world.fields.iter().any(|field| field.pointIn(&mouse_pos)) => {
+                edit(world);
+                world.fields.retain(|field| !field.pointIn(&mouse_pos));
+            },
+            (Keycode::N, _) => {
+                edit(world);
+                world.nbody = match world.nbody {
+                    Some(_) => None,
+                    None => Some(NBody::new(GRAVITATIONAL_CONSTANT)),
+                };
+            },
+            (Keycode::B, _) => {
+                if world.nbody.is_some() {
+                    edit(world);
+                }
+                if let Some(nbody) = &mut world.nbody {
+                    nbody.setBarnesHut(match nbody.barnesHut() {
+                        Some(_) => None,
+                        None => Some(BARNES_HUT_THETA),
+                    });
+                }
+            },
+            (Keycode::U, _) => {
+                edit(world);
+                world.electromagnetism.setMagneticField(if world.electromagnetism.magneticField() == 0.0 { MAGNETIC_FIELD } else { 0.0 });
+            },
+            (Keycode::L, _) => {
+                self.showFieldLines = !self.showFieldLines;
+            },
+            (Keycode::E, _) => {
+                self.message = Some(self.toggleExport());
+            },
+            (Keycode::V, _) => {
+                self.message = Some(self.toggleCapture());
+            },
+            (Keycode::I, _) => {
+                self.overlay = match self.overlay {
+                    Some(_) => None,
+                    None => Some(Overlay::new()),
+                };
+            },
+            (Keycode::M, _) => {
+                world.fluid.setRender(match world.fluid.render() {
+                    FluidRender::Points => FluidRender::Metaballs,
+                    FluidRender::Metaballs => FluidRender::Points,
+                });
+            },
+            _ => (),
+        }
+    }
+
+    //Starts writing body state as the world runs, or stops and finishes the file
+    fn toggleExport(&mut self) -> Message
+    {
+        match self.exporter.take() {
+            Some(mut exporter) => match exporter.flush() {
+                Ok(()) => Message::success(format!("Exported to {}", self.exportPath)),
+                Err(error) => Message::failure(format!("Could not export: {}", error)),
+            },
+            None => match Exporter::create(&self.exportPath) {
+                Ok(exporter) => {
+                    let exporter = exporter.with_interval(self.exportEvery);
+                    self.exporter = Some(if self.exportDiagnostics { exporter.with_diagnostics() } else { exporter });
+                    Message::success(format!("Exporting to {}", self.exportPath))
+                },
+                Err(error) => Message::failure(format!("Could not export: {}", error)),
+            },
+        }
+    }
+
+    //Starts recording what is on screen, or stops and finishes the images
+    fn toggleCapture(&mut self) -> Message
+    {
+        match self.capture.take() {
+            Some(capture) => match capture.finish() {
+                Ok(frames) => Message::success(format!("Captured {} frames to {}", frames, self.capturePath)),
+                Err(error) => Message::failure(format!("Could not capture: {}", error)),
+            },
+            None => match Capture::create(&self.capturePath, self.captureFps) {
+                Ok(capture) => {
+                    self.capture = Some(capture);
+                    Message::success(format!("Capturing to {}", self.capturePath))
+                },
+                Err(error) => Message::failure(format!("Could not capture: {}", error)),
+            },
+        }
+    }
+
+    //Closes any export or capture still running, so their files are complete
+    pub fn finish(&mut self)
+    {
+        if self.exporter.is_some() {
+            self.toggleExport();
+        }
+        if self.capture.is_some() {
+            self.toggleCapture();
+        }
+    }
+
+    //Runs one frame of input. What should be on screen is only worked out when it is to be drawn
+    //or captured, and is empty otherwise
+    pub fn advance(&mut self, frame: &Frame, draw: bool) -> Vec<(Point, Color)>
+    {
+        for input in &frame.inputs {
+            self.handle(input, frame);
+        }
+        if !draw && self.capture.is_none() {
+            self.update(frame);
+            return Vec::new();
+        }
+
+        let mut points = self.held(Vec2::from(Point::new(frame.mouse.0, frame.mouse.1)));
+        self.update(frame);
+        points.append(&mut self.display());
+        let dt = DT;
+        if let Some(Err(error)) = self.capture.as_mut().map(|capture| capture.offer(dt, || Image::from_points(&points))) {
+            self.capture = None;
+            self.message = Some(Message::failure(format!("Stopped capturing: {}", error)));
+        }
+        points
+    }
+
+    fn mouseUp(&mut self, x: i32, y: i32)
+    {
+        for button in &mut self.buttons {
+            if button.clicked() {
+                button.click_up(&self.world.objects);
+            }
+        }
+
+        match std::mem::replace(&mut self.mode, Mode::Paused(ManipMode::Move)) {
+            Mode::Paused(ManipMode::Carrying(shape, _)) |
+            Mode::Paused(ManipMode::VelSetting(shape, _)) |
+            Mode::Paused(ManipMode::AngVelSetting(shape, _)) |
+            Mode::Paused(ManipMode::Resizing(shape, _, _)) |
+            Mode::Paused(ManipMode::Rotating(shape, _, _)) => {
+                self.world.objects.push(shape);
+            },
+
+            Mode::Paused(ManipMode::Cutting(start)) => {
+                let end = Vec2::from(Point::new(x, y));
+                for body in self.world.pbd_bodies.iter_mut() {
+                    body.cut(&start, &end);
+                }
+            },
+
+            mode => self.mode = mode,
+        }
+    }
+
+    //The shape being held, or the line being cut, as it was before this frame moved it
+    fn held(&self, mouse_pos: Vec2) -> Vec<(Point, Color)>
+    {
+        match &self.mode {
+            Mode::Paused(ManipMode::Carrying(shape, _)) |
+            Mode::Paused(ManipMode::VelSetting(shape, _)) |
+            Mode::Paused(ManipMode::AngVelSetting(shape, _)) |
+            Mode::Paused(ManipMode::Resizing(shape, _, _)) |
+            Mode::Paused(ManipMode::Rotating(shape, _, _)) => shape.display(),
+
+            Mode::Paused(ManipMode::Cutting(start)) => {
+                let extr = |a: Vec2| {let a = Point::from(a); (a.x() as isize, a.y() as isize)};
+                Bresenham::new(extr(*start), extr(mouse_pos))
+                    .map(|a| (Point::new(a.0 as i32, a.1 as i32), Color::RGB(255, 0, 0)))
+                    .collect()
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    //Makes the held shape follow the mouse, then runs the world on, or back while R is held
+    fn update(&mut self, frame: &Frame)
+    {
+        let mouse_pos = Vec2::from(Point::new(frame.mouse.0, frame.mouse.1));
+        match self.mode {
+            Mode::Paused(ManipMode::Carrying(ref mut shape, ref grabPos)) => {
+                shape.translateTo(&(mouse_pos-*grabPos));
+            },
+
+            Mode::Paused(ManipMode::VelSetting(ref mut shape, ref grabPos)) => {
+                shape.impulse(&(((mouse_pos-shape.position())-*grabPos)-shape.velocity()));
+            },
+
+            Mode::Paused(ManipMode::AngVelSetting(ref mut shape, ref grabPos)) => {
+                let (_, angle) = (mouse_pos-shape.position()).polar();
+                let (_, newAngle) = grabPos.polar();
+                shape.angular_impulse((newAngle-angle)-shape.angular_velocity());
+            },
+
+            Mode::Paused(ManipMode::Resizing(ref mut shape, ref click, ref archive)) => {
+                shape.resize(click, &(mouse_pos-shape.position()), archive);
+            },
+
+            Mode::Paused(ManipMode::Rotating(ref mut shape, ref click, ref archive)) => {
+                shape.rotate(click, &(mouse_pos-shape.position()), archive);
+            },
+            _ => (),
+        }
+
+        //Holding R runs time backwards through the frames kept while running
+        match self.mode {
+            Mode::Unpaused | Mode::Paused(ManipMode::Move) if frame.rewinding => {
+                if let Some(snapshot) = self.history.pop_back() {
+                    self.world.restore(&snapshot);
+                }
+            },
+            Mode::Unpaused => {
+                self.history.push_back(self.world.snapshot());
+                if self.history.len() > REWIND_FRAMES {
+                    self.history.pop_front();
+                }
+                let dt = DT;
+                self.world.step(dt);
+                if let Some(overlay) = &mut self.overlay {
+                    overlay.record(Diagnostics::measure(&self.world));
+                }
+                if let Some(Err(error)) = self.exporter.as_mut().map(|exporter| exporter.sample(&self.world, dt)) {
+                    self.exporter = None;
+                    self.message = Some(Message::failure(format!("Stopped exporting: {}", error)));
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn display(&mut self) -> Vec<(Point, Color)>
+    {
+        let points = Arc::new(Mutex::new(self.world.display()));
+        let (electromagnetism, objects) = (&self.world.electromagnetism, &self.world.objects);
+        let (buttons, showFieldLines) = (&self.buttons, self.showFieldLines);
+        thread::scope( |s| {
+            if showFieldLines {
+                s.spawn(|_| {
+                    let p = &mut electromagnetism.fieldLines(objects).display();
+                    points.lock().unwrap().append(p);
+                });
+            }
+            for UI in buttons {
+                s.spawn(|_| {
+                    let p = &mut UI.display();
+                    points.lock().unwrap().append(p);
+                });
+            }
+        }).unwrap();
+
+        if self.message.as_ref().is_some_and(|m| m.expired()) {
+            self.message = None;
+        }
+        if let Some(overlay) = &self.overlay {
+            points.lock().unwrap().append(&mut overlay.display());
+        }
+        if let Some(m) = &self.message {
+            points.lock().unwrap().append(&mut m.display());
+        }
+
+        let points = std::mem::take(&mut *points.lock().unwrap());
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //The scene the sandbox opens with, and a sandbox on it
+    fn opened() -> (Scene, Sandbox)
+    {
+        let scene = Scene::load("scenes/sandbox.ron").unwrap();
+        let sandbox = Sandbox::new(scene.build().unwrap());
+        (scene, sandbox)
+    }
+
+    #[test]
+    fn replays_what_was_recorded()
+    {
+        let saved = std::env::temp_dir().join(format!("physics-2d-sandbox-{}.ron", std::process::id()));
+        let saved = saved.to_str().unwrap();
+
+        //Pours water, saves, drags a ball away, loads the save back, then runs with a rewind
+        let mut frames = Vec::new();
+        for i in 0..120 {
+            let mut frame = Frame::new(i, i as f64*DT);
+            frame.mouse = if (2..12).contains(&i) { (200, 800-10*(i as i32-2)) } else { (500, 700) };
+            frame.ctrl = i == 1 || i == 12;
+            frame.rewinding = (80..90).contains(&i);
+            match i {
+                0 => frame.inputs.push(Input::Key(Keycode::W as i32)),
+                1 => frame.inputs.push(Input::Key(Keycode::S as i32)),
+                2 => frame.inputs.push(Input::MouseDown{x: 200, y: 800, button: Click::Left}),
+                11 => frame.inputs.push(Input::MouseUp{x: 200, y: 710}),
+                12 => frame.inputs.push(Input::Key(Keycode::L as i32)),
+                13 => frame.inputs.push(Input::MouseDown{x: 50, y: 50, button: Click::Left}),
+                14 => frame.inputs.push(Input::MouseUp{x: 50, y: 50}),
+                _ => (),
+            }
+            frames.push(frame);
+        }
+
+        let (scene, sandbox) = opened();
+        let mut sandbox = sandbox.with_save_file(saved).with_recording();
+        let mut replay = Replay::new(scene);
+        for frame in frames {
+            sandbox.advance(&frame, false);
+            replay.record(frame);
+            replay.loads.extend(sandbox.takeLoaded());
+        }
+        assert!(matches!(sandbox.mode, Mode::Unpaused));
+        assert_eq!(replay.loads.len(), 1);
+        assert!(replay.loads[0].is_some());
+        let recorded = sandbox.world().checksum();
+
+        //The save is gone, so only the load kept in the replay can bring the scene back
+        std::fs::remove_file(saved).unwrap();
+        let replay = Replay::parse(&replay.write().unwrap()).unwrap();
+        let (_, sandbox) = opened();
+        let mut sandbox = sandbox.with_save_file(saved).with_replay(&replay);
+        for frame in replay.playback() {
+            sandbox.advance(&frame, false);
+        }
+        assert_eq!(sandbox.world().checksum(), recorded);
+        assert!(!std::path::Path::new(saved).exists());
+    }
+    #[test]
+    fn ctrl_then_z_undoes_the_last_edit()
+    {
+        let (_, mut sandbox) = opened();
+        let mut frame = Frame::new(0, 0.0);
+        frame.mouse = (500, 500);
+        let before = sandbox.world().checksum();
+
+        //Holding W pours water on every repeat, but the edit is the press
+        frame.inputs = vec![Input::Key(Keycode::W as i32), Input::Repeat(Keycode::W as i32), Input::Repeat(Keycode::W as i32)];
+        sandbox.advance(&frame, false);
+        assert_ne!(sandbox.world().checksum(), before);
+
+        //Pressing Ctrl on its own is not an edit, so Z still undoes the water
+        frame.ctrl = true;
+        frame.inputs = vec![Input::Key(Keycode::LCtrl as i32)];
+        sandbox.advance(&frame, false);
+        frame.inputs = vec![Input::Key(Keycode::Z as i32)];
+        sandbox.advance(&frame, false);
+        assert_eq!(sandbox.world().checksum(), before);
+        assert!(sandbox.undo.is_empty());
+    }
+}
@@ -0,0 +1,446 @@
+use crate::vec2::Vec2;
+use crate::world::{World, WorldSettings};
+use crate::displayable::physics::{Shape, Physics, rect::Rect, circle::Circle, body::CollisionFilter, surface::Surface, drag::Drag, softBody::SoftBody, pbd::PbdBody, particles::Emitter, buoyancy::{FluidRegion, area, convex}, nbody::NBody, electromagnetism::Electromagnetism};
+
+use serde::{Serialize, Deserialize};
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+//Bumped whenever a change stops old files from meaning the same thing. Files from newer versions
+//are refused rather than half understood
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Geometry
+{
+    Circle{radius: f64},
+    Rect{size: Vec2, #[serde(default)] rotation: f64},
+}
+
+//Properties shared by every body made of the same stuff. Bodies name the material they use
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Material
+{
+    //Mass per unit area, for bodies that do not give a mass
+    pub density: f64,
+    pub surface: Surface,
+    pub drag: Drag,
+    pub break_impulse: Option<f64>,
+}
+
+impl Material
+{
+    pub fn new() -> Material
+    {
+        Material{density: 1.0, surface: Surface::Normal, drag: Drag::none(), break_impulse: None}
+    }
+}
+
+impl Default for Material
+{
+    fn default() -> Material
+    {
+        Material::new()
+    }
+}
+
+//One body as written in a scene file. Anything left out takes the same default the constructors
+//use, and anything given here overrides the material
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodyDesc
+{
+    pub geometry: Geometry,
+    pub position: Vec2,
+    #[serde(default = "Vec2::zero")]
+    pub velocity: Vec2,
+    #[serde(default)]
+    pub angular_velocity: f64,
+    //None to work the mass out from the area and density
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mass: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub material: Option<String>,
+    #[serde(default)]
+    pub fixed: bool,
+    #[serde(default)]
+    pub sensor: bool,
+    #[serde(default)]
+    pub filter: CollisionFilter,
+    #[serde(default)]
+    pub charge: f64,
+    #[serde(default = "Vec2::zero")]
+    pub dipole: Vec2,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub surface: Option<Surface>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drag: Option<Drag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub break_impulse: Option<f64>,
+}
+
+impl BodyDesc
+{
+    pub fn from_shape(shape: &Shape) -> BodyDesc
+    {
+        let (geometry, surface) = match shape {
+            Shape::Circle(circle) => (Geometry::Circle{radius: circle.radius()}, None),
+            Shape::Rect(rect) => (Geometry::Rect{size: rect.size(), rotation: rect.rotation()}, Some(rect.surface())),
+        };
+        let body = shape.body();
+        BodyDesc{
+            geometry,
+            position: shape.position(),
+            velocity: shape.velocity(),
+            angular_velocity: shape.angular_velocity(),
+            mass: Some(shape.mass()),
+            material: None,
+            fixed: body.fixed,
+            sensor: body.sensor,
+            filter: body.filter,
+            charge: body.charge,
+            dipole: body.dipole,
+            surface: surface.filter(|s| *s != Surface::Normal),
+            drag: Some(body.drag).filter(|d| *d != Drag::none()),
+            break_impulse: body.break_impulse,
+        }
+    }
+
+    fn build(&self, material: &Material) -> Shape
+    {
+        let surface = self.surface.unwrap_or(material.surface);
+        let mut shape = match self.geometry {
+            Geometry::Circle{radius} => {
+                let mass = self.mass.unwrap_or(material.density*std::f64::consts::PI*radius.powi(2));
+                Shape::Circle(Circle::new_with_mass(self.position, radius, mass))
+            },
+            Geometry::Rect{size, rotation} => {
+                let mass = self.mass.unwrap_or(material.density*(size.x()*size.y()).abs());
+                Shape::Rect(Rect::from_centre_with_mass(self.position, size, rotation, mass).with_surface(surface))
+            },
+        };
+        let body = shape.mutBody();
+        body.sensor = self.sensor;
+        body.filter = self.filter;
+        body.charge = self.charge;
+        body.dipole = self.dipole;
+        body.drag = self.drag.unwrap_or(material.drag);
+        body.break_impulse = self.break_impulse.or(material.break_impulse);
+        if self.fixed {
+            return shape.fixed();
+        }
+        shape.impulse(&self.velocity);
+        shape.angular_impulse(self.angular_velocity);
+        shape
+    }
+
+    fn validate(&self) -> Result<(), String>
+    {
+        match self.geometry {
+            Geometry::Circle{radius} if radius <= 0.0 => return Err(format!("radius must be positive, not {}", radius)),
+            Geometry::Rect{size, ..} if size.x() == 0.0 || size.y() == 0.0 => return Err("rects cannot have a zero side".to_string()),
+            _ => (),
+        }
+        match self.mass {
+            Some(mass) if mass <= 0.0 => Err(format!("mass must be positive, not {}", mass)),
+            _ => Ok(()),
+        }
+    }
+}
+
+//Bodies held together by springs or distance constraints. The shorthand forms are built with the
+//same constructors as in code, saving always writes out every point so cuts and stretching survive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum JointDesc
+{
+    Rope{start: Vec2, end: Vec2, segments: usize, mass: f64, #[serde(default)] compliance: f64},
+    Cloth{top_left: Vec2, size: Vec2, cols: usize, rows: usize, mass: f64, #[serde(default)] compliance: f64},
+    SoftRect{centre: Vec2, size: Vec2, cols: usize, rows: usize, mass: f64, stiffness: f64, damping: f64, #[serde(default)] pressure: Option<f64>},
+    SoftCircle{centre: Vec2, radius: f64, segments: usize, mass: f64, stiffness: f64, damping: f64, #[serde(default)] pressure: Option<f64>},
+    Pbd(PbdBody),
+    Soft(SoftBody),
+}
+
+impl JointDesc
+{
+    fn validate(&self) -> Result<(), String>
+    {
+        let inRange = |ends: Vec<usize>, count: usize| match ends.into_iter().find(|i| *i >= count) {
+            Some(i) => Err(format!("refers to point {} but only has {}", i, count)),
+            None => Ok(()),
+        };
+        let positive = |what: &str, values: Vec<f64>| match values.into_iter().find(|value| *value <= 0.0) {
+            Some(value) => Err(format!("{} must be positive, not {}", what, value)),
+            None => Ok(()),
+        };
+        match self {
+            JointDesc::Rope{mass, ..} | JointDesc::Cloth{mass, ..} | JointDesc::SoftRect{mass, ..} | JointDesc::SoftCircle{mass, ..} => positive("mass", vec![*mass])?,
+            JointDesc::Pbd(body) => {
+                positive("mass", body.particles().iter().map(|p| p.mass()).collect())?;
+                positive("rest length", body.constraints().iter().map(|c| c.restLength()).collect())?;
+            },
+            JointDesc::Soft(body) => {
+                positive("mass", body.points().iter().map(|p| p.mass()).collect())?;
+                positive("rest length", body.springs().iter().map(|s| s.restLength()).collect())?;
+            },
+        }
+        match self {
+            JointDesc::SoftRect{size, ..} if size.x() <= 0.0 || size.y() <= 0.0 => Err("a soft rect needs a positive size".to_string()),
+            JointDesc::SoftCircle{radius, ..} if *radius <= 0.0 => Err(format!("radius must be positive, not {}", radius)),
+            JointDesc::Rope{segments, ..} if *segments < 1 => Err("a rope needs at least 1 segment".to_string()),
+            JointDesc::Cloth{cols, rows, ..} | JointDesc::SoftRect{cols, rows, ..} if *cols < 2 || *rows < 2 => Err("needs at least 2 points in each direction".to_string()),
+            JointDesc::SoftCircle{segments, ..} if *segments < 3 => Err("a soft circle needs at least 3 segments".to_string()),
+            JointDesc::Pbd(body) => inRange(body.constraints().iter().flat_map(|c| [c.ends().0, c.ends().1]).collect(), body.particles().len()),
+            JointDesc::Soft(body) => {
+                inRange(body.springs().iter().flat_map(|s| [s.ends().0, s.ends().1]).collect(), body.points().len())?;
+                inRange(body.hull().to_vec(), body.points().len())
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+//A world as it is stored on disk, in RON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scene
+{
+    pub version: u32,
+    #[serde(default = "Vec2::zero")]
+    pub gravity: Vec2,
+    #[serde(default)]
+    pub settings: WorldSettings,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbody: Option<NBody>,
+    #[serde(default)]
+    pub electromagnetism: Electromagnetism,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub materials: BTreeMap<String, Material>,
+    #[serde(default)]
+    pub bodies: Vec<BodyDesc>,
+    #[serde(default)]
+    pub joints: Vec<JointDesc>,
+    #[serde(default)]
+    pub regions: Vec<FluidRegion>,
+    #[serde(default)]
+    pub emitters: Vec<Emitter>,
+}
+
+impl Scene
+{
+    //Everything needed to build the world again. Fluid, particles in flight and force fields are
+    //left out, they are effects rather than part of the scene
+    pub fn from_world(world: &World) -> Scene
+    {
+        let mut joints: Vec<JointDesc> = world.pbd_bodies.iter().cloned().map(JointDesc::Pbd).collect();
+        joints.extend(world.soft_bodies.iter().cloned().map(JointDesc::Soft));
+        Scene{
+            version: VERSION,
+            gravity: world.gravity,
+            settings: world.settings,
+            nbody: world.nbody,
+            electromagnetism: world.electromagnetism,
+            materials: BTreeMap::new(),
+            bodies: world.objects.iter().map(BodyDesc::from_shape).collect(),
+            joints,
+            regions: world.regions.clone(),
+            emitters: world.particles.emitters().to_vec(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Scene, SceneError>
+    {
+        let scene: Scene = ron::from_str(text).map_err(|error| SceneError::Parse{path: None, line: error.position.line, column: error.position.col, message: error.code.to_string()})?;
+        if scene.version > VERSION {
+            return Err(SceneError::Version{found: scene.version, supported: VERSION});
+        }
+        Ok(scene)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Scene, SceneError>
+    {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|error| SceneError::Io{path: path.to_path_buf(), error})?;
+        Scene::parse(&text).map_err(|error| match error {
+            SceneError::Parse{line, column, message, ..} => SceneError::Parse{path: Some(path.to_path_buf()), line, column, message},
+            error => error,
+        })
+    }
+
+    pub fn write(&self) -> Result<String, SceneError>
+    {
+        let config = ron::ser::PrettyConfig::new().depth_limit(3);
+        ron::ser::to_string_pretty(self, config).map_err(|error| SceneError::Invalid(error.to_string()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneError>
+    {
+        let path = path.as_ref();
+        fs::write(path, self.write()?).map_err(|error| SceneError::Io{path: path.to_path_buf(), error})
+    }
+
+    pub fn build(&self) -> Result<World, SceneError>
+    {
+        let mut world = World::new();
+        world.gravity = self.gravity;
+        world.settings = self.settings;
+        world.nbody = self.nbody;
+        world.electromagnetism = self.electromagnetism;
+
+        for (i, desc) in self.bodies.iter().enumerate() {
+            desc.validate().map_err(|message| SceneError::Invalid(format!("body {}: {}", i, message)))?;
+            let material = match &desc.material {
+                Some(name) => *self.materials.get(name).ok_or(SceneError::UnknownMaterial{body: i, name: name.clone()})?,
+                None => Material::new(),
+            };
+            world.objects.push(desc.build(&material));
+        }
+
+        for (i, joint) in self.joints.iter().enumerate() {
+            joint.validate().map_err(|message| SceneError::Invalid(format!("joint {}: {}", i, message)))?;
+            match joint.clone() {
+                JointDesc::Rope{start, end, segments, mass, compliance} => world.pbd_bodies.push(PbdBody::rope(start, end, segments, mass, compliance)),
+                JointDesc::Cloth{top_left, size, cols, rows, mass, compliance} => world.pbd_bodies.push(PbdBody::cloth(top_left, size, cols, rows, mass, compliance)),
+                JointDesc::SoftRect{centre, size, cols, rows, mass, stiffness, damping, pressure} => {
+                    let body = SoftBody::rect(centre, size, cols, rows, mass, stiffness, damping);
+                    world.soft_bodies.push(match pressure { Some(p) => body.with_pressure(p), None => body });
+                },
+                JointDesc::SoftCircle{centre, radius, segments, mass, stiffness, damping, pressure} => {
+                    let body = SoftBody::circle(centre, radius, segments, mass, stiffness, damping);
+                    world.soft_bodies.push(match pressure { Some(p) => body.with_pressure(p), None => body });
+                },
+                JointDesc::Pbd(body) => world.pbd_bodies.push(body),
+                JointDesc::Soft(body) => world.soft_bodies.push(body),
+            }
+        }
+
+        for (i, region) in self.regions.iter().enumerate() {
+            if region.points().len() < 3 {
+                return Err(SceneError::Invalid(format!("region {}: needs at least 3 points", i)));
+            }
+            //Built again so clockwise polygons are turned round, as they would be in code
+            let (linear, angular) = region.drag();
+            let region = FluidRegion::polygon(region.points().to_vec(), region.density()).with_drag(linear, angular);
+            if area(region.points()) == 0.0 || !convex(region.points()) {
+                return Err(SceneError::Invalid(format!("region {}: must be a convex polygon", i)));
+            }
+            world.regions.push(region);
+        }
+        for emitter in &self.emitters {
+            world.particles.addEmitter(emitter.clone());
+        }
+        Ok(world)
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError
+{
+    Io{path: PathBuf, error: io::Error},
+    //Line and column are where the file stopped making sense, counting from 1
+    Parse{path: Option<PathBuf>, line: usize, column: usize, message: String},
+    Version{found: u32, supported: u32},
+    UnknownMaterial{body: usize, name: String},
+    //Parsed fine but describes something that cannot be built
+    Invalid(String),
+}
+
+impl fmt::Display for SceneError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            SceneError::Io{path, error} => write!(f, "could not access {}: {}", path.display(), error),
+            SceneError::Parse{path: Some(path), line, column, message} => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            SceneError::Parse{path: None, line, column, message} => write!(f, "line {}, column {}: {}", line, column, message),
+            SceneError::Version{found, supported} => write!(f, "scene is version {}, but only versions up to {} are supported", found, supported),
+            SceneError::UnknownMaterial{body, name} => write!(f, "body {} uses material \"{}\", which the scene does not define", body, name),
+            SceneError::Invalid(message) => write!(f, "invalid scene: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn builds_a_world()
+    {
+        let scene = Scene::parse("(
+            version: 1,
+            gravity: (x: 0.0, y: -500.0),
+            materials: {\"ice\": (density: 0.5, surface: Bouncy(1.2))},
+            bodies: [
+                (geometry: Circle(radius: 10.0), position: (x: 0.0, y: 100.0), velocity: (x: 20.0, y: 0.0)),
+                (geometry: Rect(size: (x: 800.0, y: 20.0)), position: (x: 0.0, y: -300.0), material: Some(\"ice\"), fixed: true),
+            ],
+            joints: [Rope(start: (x: 0.0, y: 400.0), end: (x: 0.0, y: 200.0), segments: 10, mass: 100.0)],
+        )").unwrap();
+        let world = scene.build().unwrap();
+        assert_eq!(world.gravity, Vec2::new(0.0, -500.0));
+        assert_eq!(world.objects.len(), 2);
+        assert_eq!(world.objects[0].velocity(), Vec2::new(20.0, 0.0));
+        assert!(world.objects[1].body().fixed);
+        assert_eq!(world.pbd_bodies.len(), 1);
+        match &world.objects[1] {
+            Shape::Rect(rect) => assert_eq!(rect.surface(), Surface::Bouncy(1.2)),
+            _ => panic!("expected a rect"),
+        }
+    }
+    #[test]
+    fn saves_and_loads()
+    {
+        let mut world = World::new();
+        world.gravity = Vec2::new(0.0, -100.0);
+        world.objects.push(Shape::Circle(Circle::new(Vec2::new(5.0, 5.0), 10.0)).with_charge(3.0));
+        world.objects[0].impulse(&Vec2::new(1.0, 2.0));
+        world.objects.push(Shape::Rect(Rect::from_centre(Vec2::zero(), Vec2::new(50.0, 10.0), 0.4)).fixed().breakable(500.0));
+        world.pbd_bodies.push(PbdBody::rope(Vec2::zero(), Vec2::new(100.0, 0.0), 5, 10.0, 0.0));
+
+        let text = Scene::from_world(&world).write().unwrap();
+        let loaded = Scene::parse(&text).unwrap().build().unwrap();
+        assert_eq!(loaded.gravity, world.gravity);
+        for (a, b) in world.objects.iter().zip(&loaded.objects) {
+            assert_eq!(a.position(), b.position());
+            assert_eq!(a.velocity(), b.velocity());
+            assert_eq!(a.body().charge, b.body().charge);
+            assert_eq!(a.body().break_impulse, b.body().break_impulse);
+        }
+        assert_eq!(loaded.pbd_bodies[0].particles().len(), 6);
+    }
+    #[test]
+    fn explains_bad_files()
+    {
+        let error = Scene::parse("(version: 1, bodies: [(position: (x: 0.0, y: 0.0))])").unwrap_err();
+        assert!(matches!(error, SceneError::Parse{line: 1, ..}), "{}", error);
+        assert!(error.to_string().contains("geometry"), "{}", error);
+        assert!(matches!(Scene::parse("(version: 99)"), Err(SceneError::Version{found: 99, ..})));
+        let unknown = Scene::parse("(version: 1, bodies: [(geometry: Circle(radius: 1.0), position: (x: 0.0, y: 0.0), material: Some(\"lead\"))])").unwrap();
+        assert!(matches!(unknown.build(), Err(SceneError::UnknownMaterial{body: 0, ..})));
+        let weightless = Scene::parse("(version: 1, joints: [SoftCircle(centre: (x: 0.0, y: 0.0), radius: 20.0, segments: 8, mass: 0.0, stiffness: 1.0, damping: 1.0)])").unwrap();
+        assert!(matches!(weightless.build(), Err(SceneError::Invalid(message)) if message.contains("joint 0: mass")));
+        let collapsed = Scene{joints: vec![JointDesc::Pbd(PbdBody::rope(Vec2::zero(), Vec2::zero(), 5, 10.0, 0.0))], ..Scene::parse("(version: 1)").unwrap()};
+        assert!(matches!(collapsed.build(), Err(SceneError::Invalid(message)) if message.contains("rest length")));
+    }
+    #[test]
+    fn checks_fluid_regions()
+    {
+        //A clockwise square is turned round, an L-shape cannot be used to clip bodies
+        let square = "(polygon: [(x: 0.0, y: 0.0), (x: 0.0, y: 10.0), (x: 10.0, y: 10.0), (x: 10.0, y: 0.0)], density: 1.0, linear_drag: 2.0, angular_drag: 3.0)";
+        let world = Scene::parse(&format!("(version: 1, regions: [{}])", square)).unwrap().build().unwrap();
+        assert!(area(world.regions[0].points()) > 0.0);
+        assert_eq!(world.regions[0].drag(), (2.0, 3.0));
+
+        let corner = "(polygon: [(x: 0.0, y: 0.0), (x: 20.0, y: 0.0), (x: 20.0, y: 10.0), (x: 10.0, y: 10.0), (x: 10.0, y: 20.0), (x: 0.0, y: 20.0)], density: 1.0, linear_drag: 1.0, angular_drag: 1.0)";
+        match Scene::parse(&format!("(version: 1, regions: [{}, {}])", square, corner)).unwrap().build() {
+            Err(error @ SceneError::Invalid(_)) => assert!(error.to_string().contains("region 1"), "{}", error),
+            Err(error) => panic!("{}", error),
+            Ok(_) => panic!("built a non-convex region"),
+        }
+    }
+}
@@ -3,7 +3,9 @@ use std::ops::*;
 use sdl2::rect::Point;
 use crate::WINDOW_DIMENSIONS;
 
-#[derive(Clone, Copy, Debug)]
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Vec2
 {
     x: f64,
@@ -32,13 +34,15 @@ impl Vec2
         self.y
     }
 
+    //Not powf: an optimised build swaps powf(2.0) and powf(0.5) for a multiply and a square root,
+    //which libm's pow does not always round the same way, and deterministic runs must match
     pub fn len_squared(&self) -> f64
     {
-        ((self.x as f64).powf(2.0_f64)+(self.y as f64).powf(2.0_f64)) as f64
+        self.x*self.x+self.y*self.y
     }
     pub fn len(&self) -> f64
     {
-        (self.len_squared() as f64).powf(0.5_f64)
+        self.len_squared().sqrt()
     }
     pub fn dot(fst: &Vec2, snd: &Vec2) -> f64
     {